@@ -28,20 +28,26 @@ impl<T> BlockHandle<T> {
     }
 }
 
+// The block must be freed with deallocate_block(), using the same layout
 pub fn allocate_block<T>(layout: alloc::Layout) -> BlockHandle<T> {
-    // TODO: this is a memory leak. We should clean this up when the definition is no longer in use.
     let ptr= unsafe {
         std::alloc::alloc(layout) as *mut T
     };
 
     BlockHandle {
-        ptr: ptr,
+        ptr,
         phantom: marker::PhantomData
     }
 }
 
+// Frees a block allocated with allocate_block(). Anything in the block with drop glue must be dropped first,
+// and nothing may point into the block afterwards.
+pub(crate) unsafe fn deallocate_block<T>(block_handle: &BlockHandle<T>, layout: alloc::Layout) {
+    std::alloc::dealloc(block_handle.ptr as *mut u8, layout);
+}
+
 // A pointer to an item within the block handle.
-// `offset` MUST be properly aligned, std::alloc::Layout should be used when allocating the
+// `offset` is in bytes, and MUST be properly aligned, std::alloc::Layout should be used when allocating the
 // block to make this guarantee.
 pub struct BlockPointer<T> {
     handle: BlockHandle<T>,
@@ -51,13 +57,13 @@ pub struct BlockPointer<T> {
 impl<T> BlockPointer<T> {
     pub fn get_pointer(&self) -> *const T {
         unsafe {
-            self.handle.get_pointer().add(self.offset)
+            (self.handle.get_pointer() as *const u8).add(self.offset) as *const T
         }
     }
 
     pub fn get_pointer_mut(&self) -> *mut T {
         unsafe {
-            self.handle.get_pointer_mut().add(self.offset)
+            (self.handle.get_pointer_mut() as *mut u8).add(self.offset) as *mut T
         }
     }
 
@@ -79,10 +85,10 @@ impl<T> BlockPointer<T> {
     pub fn from_raw_parts(ptr: *mut T, offset: usize) -> BlockPointer<T> {
         BlockPointer {
             handle: BlockHandle {
-                ptr: ptr,
+                ptr,
                 phantom: marker::PhantomData,
             },
-            offset: offset,
+            offset,
         }
     }
 }
@@ -2,21 +2,35 @@ pub mod block;
 mod schema_types;
 mod schema_string;
 mod schema_array;
-mod parser;
+mod schema_object;
+mod schema_error;
+mod unknown_variant;
+pub mod parser;
 
 use schema_macros::Schematize;
 pub use schema_array::SchemaArray;
 pub use schema_string::SchemaString;
+pub use schema_object::{SchemaObject, SchemaField};
+pub use schema_error::{SchemaError, SchemaErrors, SchemaErrorKind};
+pub use unknown_variant::UnknownVariant;
+pub use parser::Span;
 
-use std::collections;
 use std::vec::Vec;
 use std::env;
 use std::alloc;
+use std::collections;
+use std::any;
+
+#[derive(Debug)]
+pub struct SchemaValue<'a> {
+    pub kind: SchemaValueKind<'a>,
+    pub span: Span,    // Location of this value in the definition file
+}
 
 #[derive(Debug)]
-pub enum SchemaValue<'a> {
+pub enum SchemaValueKind<'a> {
     // represents a schematized struct
-    Object(collections::HashMap<&'a str, SchemaValue<'a>>),
+    Object(SchemaObject<'a>),
     Integer(i64),
     Decimal(f64),
     Bool(bool),
@@ -26,6 +40,8 @@ pub enum SchemaValue<'a> {
     String(&'a str),
     EnumVariant(&'a str, Box<SchemaValue<'a>>),
     Null,
+    // the `default` literal, stands for the schema default of whatever type is expected here
+    Default,
 
     // TODO:
     //   Impl (schema owner pointer/mix in pattern)
@@ -35,47 +51,326 @@ pub enum SchemaValue<'a> {
     //     - point to an entry of an array
 }
 
-#[derive(Debug)]
-pub enum SchemaError {
-    WrongSchemaValue,
-    MissingField,
-    WrongSizedArray,
-    NumberOutOfBounds,
-    UnknownField,
-    UnknownIdentifier,
+impl<'a> SchemaValueKind<'a> {
+    // A short description of this value for error messages, e.g. `integer 3`
+    pub fn describe(&self) -> String {
+        match self {
+            SchemaValueKind::Object(_) => String::from("object"),
+            SchemaValueKind::Integer(num) => format!("integer {}", num),
+            SchemaValueKind::Decimal(num) => format!("decimal {:?}", num),
+            SchemaValueKind::Bool(bool) => format!("bool {}", bool),
+            SchemaValueKind::Array(vector) => format!("array of {} element(s)", vector.len()),
+            SchemaValueKind::String(str) => format!("string {:?}", str),
+            SchemaValueKind::EnumVariant(name, _) => format!("enum variant {}", name),
+            SchemaValueKind::Null => String::from("null"),
+            SchemaValueKind::Default => String::from("default"),
+        }
+    }
+}
+
+impl<'a> SchemaValue<'a> {
+    // Write this value back out in definition file syntax, e.g. to preserve an unknown field
+    pub fn serialize(&self, context: &mut SerializeContext) {
+        match &self.kind {
+            SchemaValueKind::Object(fields_map) => {
+                if fields_map.is_empty() {
+                    context.print("{}");
+                    return;
+                }
+
+                context.print("{\n");
+                context.tabs+= 1;
+                context.print_tabs();
+                serialize_fields(fields_map, context);
+                context.tabs-= 1;
+                context.println();
+                context.print_tabs();
+                context.print("}");
+            },
+            SchemaValueKind::Integer(num) => context.print(&num.to_string()),
+            SchemaValueKind::Decimal(num) => num.serialize(context),
+            SchemaValueKind::Bool(bool) => context.print(&bool.to_string()),
+            SchemaValueKind::Array(vector) => {
+                if vector.is_empty() {
+                    context.print("[]");
+                    return;
+                }
+
+                context.print("[");
+                context.tabs+= 1;
+                context.println();
+                context.print_tabs();
+                serialize_elements(vector, context);
+                context.tabs-= 1;
+                context.println();
+                context.print_tabs();
+                context.print("]");
+            },
+            SchemaValueKind::String(str) => schema_string::serialize_str(str, context),
+            SchemaValueKind::EnumVariant(name, enum_field) => {
+                serialize_variant_name(name, context);
+
+                if !matches!(enum_field.kind, SchemaValueKind::Null) {
+                    context.print(" {");
+                    context.tabs+= 1;
+                    context.println();
+                    context.print_tabs();
+                    enum_field.serialize_payload(context);
+                    context.println();
+                    context.tabs-= 1;
+                    context.print_tabs();
+                    context.print("}");
+                }
+            },
+            SchemaValueKind::Null => (),
+            SchemaValueKind::Default => context.print("default"),
+        }
+    }
+
+    // Write the payload of an enum variant, the part between the braces of `Name { ... }`. Like the parser,
+    // the fields of an object and several values are written inline, without their own brackets.
+    pub fn serialize_payload(&self, context: &mut SerializeContext) {
+        match &self.kind {
+            SchemaValueKind::Object(fields_map) if !fields_map.is_empty() => serialize_fields(fields_map, context),
+            // A single value in braces is the value itself, so a one element array keeps its brackets
+            SchemaValueKind::Array(vector) if vector.len() > 1 => serialize_elements(vector, context),
+            _ => self.serialize(context),
+        }
+    }
+}
+
+// Variants which collide with a value keyword are written as raw identifiers
+fn serialize_variant_name(name: &str, context: &mut SerializeContext) {
+    match name {
+        "true" | "false" | "inf" | "nan" | "default" => context.print(&format!("r#{}", name)),
+        _ => context.print(name),
+    }
+}
+
+fn serialize_fields(fields_map: &SchemaObject, context: &mut SerializeContext) {
+    for (index, (name, value)) in fields_map.iter().enumerate() {
+        if index > 0 {
+            context.print(",\n");
+            context.print_tabs();
+        }
+        context.print(&format!("{}: ", name));
+        value.serialize(context);
+    }
+}
+
+fn serialize_elements(vector: &[SchemaValue], context: &mut SerializeContext) {
+    for (index, element) in vector.iter().enumerate() {
+        if index > 0 {
+            context.print(",\n");
+            context.print_tabs();
+        }
+        element.serialize(context);
+    }
 }
 
 type SchemaResult<T>= Result<T, SchemaError>;
 
+// Joins a field path, e.g. [".inner", ".point", "[2]"] -> inner.point[2]
+fn join_path(path: &[String]) -> String {
+    let full_path= path.join("");
+    match full_path.strip_prefix('.') {
+        // trim off the first character which is a duplicate period
+        Some(trimmed_path) => String::from(trimmed_path),
+        None => full_path,
+    }
+}
+
+// A field kept by #[schema(unknown_fields = "collect")], so it is written back out when the definition is serialized
+#[derive(Debug, Clone)]
+pub struct UnknownField {
+    pub name: String,
+    pub value: String,  // the value in definition file syntax
+}
+
+// Unknown fields, keyed by the path of the object they were found in
+pub type UnknownFields= collections::HashMap<String, Vec<UnknownField>>;
+
 pub struct DeserializeContext {
     block_ptr: *mut u8,    // the allocated block of memory to deserialize into
     offsets: Vec<usize>,   // built recursively in Schematize::build_layout()
     offset_index: usize,   // incremented in recursive Schematize::deserialize() calls
 
-    // TODO: this should be debug only
     path: Vec<String>, // The field path when deserializing nested objects, e.g. inner.point.x
+    errors: Vec<SchemaError>, // errors from values that were replaced by their default so deserializing could go on
+    warnings: Vec<SchemaError>, // problems that don't stop the definition from loading, e.g. ignored unknown fields
+    unknown_fields: UnknownFields,
+    flattened: bool,    // the struct being deserialized shares its object with the enclosing struct
+    root_type: &'static str,    // the type name of the definition, the only value that has a version
 }
 
 impl DeserializeContext {
     pub fn get_path(&self) -> String {
-        let full_path=self.path.join("");
-        if !full_path.is_empty() {
-            // trim off the first character which is a duplicate period
-            let mut chars= full_path.chars();
-            chars.next();
-            String::from(chars.as_str())
-        } else {
-            full_path
+        join_path(&self.path)
+    }
+
+    // Records the errors returned by a #[schema(post_load = ...)] hook. Their paths are relative to the value
+    // the hook ran on, as written in the definition file (e.g. `bounds.max`), and unless they already have
+    // a location they point at the field named by the path.
+    pub fn post_load_errors(&mut self, errors: SchemaErrors, schema_value: &SchemaValue, type_name: &'static str) {
+        for error in errors {
+            let relative_path= String::from(error.path());
+            let mut path= self.path.clone();
+            if relative_path.starts_with('[') {
+                path.push(relative_path.clone());
+            } else if !relative_path.is_empty() {
+                path.push(format!(".{}", relative_path));
+            }
+
+            let span= error.span().unwrap_or_else(|| {
+                let field_name= relative_path.split(['.', '[']).next().unwrap_or("");
+                match &schema_value.kind {
+                    SchemaValueKind::Object(fields_map) =>
+                        fields_map.get(field_name).map_or(schema_value.span, |field_value| field_value.span),
+                    _ => schema_value.span,
+                }
+            });
+
+            let error= match error.type_name() {
+                Some(_) => error,
+                None => error.with_type_name(type_name),
+            };
+            self.errors.push(error.with_path(join_path(&path)).with_span(span));
         }
     }
+
+    // The version header of a definition file is the version of its root type, and only the root is migrated.
+    // So a #[schema(version = N)] type nested in a definition (or flattened into one) fails to load, instead
+    // of silently skipping its migrations.
+    pub fn check_versioned_root<T: Schematize>(&self, schema_value: &SchemaValue) -> SchemaResult<()> {
+        if any::type_name::<T>() == self.root_type {
+            return Ok(());
+        }
+
+        Err(self.error(SchemaErrorKind::Version, schema_value)
+            .with_type_name(any::type_name::<T>())
+            .with_expected(format!("version {} to only be set on the root type of a definition file, {}", T::VERSION, self.root_type))
+            .with_found("a nested value"))
+    }
+
+    // Deserialize a #[schema(flatten)] field from the object of the enclosing struct. The enclosing struct
+    // checks for unknown fields, since the object also holds the fields of every other struct sharing it.
+    pub fn deserialize_flattened<T: SchemaFlatten>(&mut self, schema_value: &SchemaValue) -> SchemaResult<T> {
+        self.flattened= true;
+        let result= T::deserialize(schema_value, self);
+        self.flattened= false;
+        result
+    }
+
+    // Keep an unknown field of the object at the current path
+    pub fn collect_unknown_field(&mut self, field: &SchemaField) {
+        let mut value_context= SerializeContext::new();
+        field.value.serialize(&mut value_context);
+
+        let unknown_field= UnknownField {
+            name: String::from(field.name),
+            value: value_context.string,
+        };
+        self.unknown_fields.entry(self.get_path()).or_default().push(unknown_field);
+    }
+
+    // Build an error for a schema value that failed to deserialize at the current field path
+    pub fn error(&self, kind: SchemaErrorKind, schema_value: &SchemaValue) -> SchemaError {
+        SchemaError::new(kind)
+            .with_path(self.get_path())
+            .with_found(schema_value.kind.describe())
+            .with_span(schema_value.span)
+    }
+
+    // Records the error for a value that failed to deserialize, and returns the default value to use in its place.
+    // Deserializing can stop partway through the value, so skip over all of the offsets that build_layout()
+    // reserved for it; the values that follow still need to line up with their own offsets.
+    pub fn recover<T: Schematize>(&mut self, error: SchemaError, schema_value: &SchemaValue, offset_index: usize) -> T {
+        self.recover_with(error, schema_value, offset_index, T::build_layout, T::schema_default)
+    }
+
+    // Same as recover(), for a field whose layout and default come from a #[schema(with = ...)] codec
+    pub fn recover_with<T>(
+        &mut self,
+        error: SchemaError,
+        schema_value: &SchemaValue,
+        offset_index: usize,
+        build_layout: fn(&SchemaValue, alloc::Layout, &mut Vec<usize>) -> BuildLayoutResult,
+        schema_default: fn() -> T,
+    ) -> T {
+        let mut offsets= Vec::new();
+        if build_layout(schema_value, alloc::Layout::new::<u8>(), &mut offsets).is_ok() {
+            self.offset_index= offset_index + offsets.len();
+        }
+
+        self.errors.push(error);
+        schema_default()
+    }
 }
 
 pub struct SerializeContext {
     string: String,
     tabs: i16,
+
+    path: Vec<String>,  // The field path of the object being serialized, used to look up unknown fields
+    unknown_fields: UnknownFields,  // written back out at the end of the object they were found in
+    flattened: bool,    // the struct being serialized writes its fields into the object of the enclosing struct
 }
 
 impl SerializeContext {
+    fn new() -> SerializeContext {
+        SerializeContext {
+            string: String::new(),
+            tabs: 0,
+            path: Vec::new(),
+            unknown_fields: UnknownFields::new(),
+            flattened: false,
+        }
+    }
+
+    // Write a #[schema(flatten)] field inline, as fields of the enclosing struct. `has_fields` is whether any
+    // fields were written before it, returns whether any have been written after it.
+    pub fn print_flattened<T: SchemaFlatten>(&mut self, value: &T, has_fields: bool) -> bool {
+        let length= self.string.len();
+        if has_fields {
+            self.print(",\n");
+            self.print_tabs();
+        }
+
+        let fields_start= self.string.len();
+        self.flattened= true;
+        value.serialize(self);
+        self.flattened= false;
+
+        // Nothing was written, so take back the separator
+        if self.string.len() == fields_start {
+            self.string.truncate(length);
+            return has_fields;
+        }
+        true
+    }
+
+    // Write out the unknown fields kept for the object at the current path, after its known fields
+    fn print_unknown_fields(&mut self, has_fields: bool) {
+        let unknown_fields= match self.unknown_fields.remove(&join_path(&self.path)) {
+            Some(unknown_fields) => unknown_fields,
+            None => return,
+        };
+
+        // The values were serialized without indentation
+        let mut indent= String::from("\n");
+        for _ in 0..self.tabs {
+            indent.push_str("  ");
+        }
+
+        for (index, unknown_field) in unknown_fields.iter().enumerate() {
+            if has_fields || index > 0 {
+                self.print(",\n");
+                self.print_tabs();
+            }
+            self.print(&format!("{}: {}", unknown_field.name, unknown_field.value.replace('\n', &indent)));
+        }
+    }
+
     fn print(&mut self, content: &str) {
         self.string.push_str(content);
     }
@@ -87,13 +382,17 @@ impl SerializeContext {
     }
 
     fn println(&mut self) {
-        self.string.push_str("\n");
+        self.string.push('\n');
     }
 }
 
 type BuildLayoutResult = Result<alloc::Layout, alloc::LayoutError>;
 
 pub trait Schematize {
+    // The version of this type's schema, set with #[schema(version = N)]. The version of a definition
+    // file is the version of its root type, older files are upgraded with parser::Migrations.
+    const VERSION: u32= 0;
+
     fn schema_default() -> Self;
 
     // Write the data of this object to a string. This is the inverse of parser::load_definition<>
@@ -107,6 +406,29 @@ pub trait Schematize {
     }
 
     fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> where Self: Sized;
+
+    // The names a struct reads from its object, including aliases and the fields of #[schema(flatten)] fields.
+    // Only structs have any, this is how a struct knows which fields of its object belong to a flattened field.
+    fn field_names(_names: &mut Vec<&'static str>) {
+    }
+}
+
+// Implemented by #[derive(Schematize)] for the types which can be #[schema(flatten)]ed into a struct, i.e. structs
+// with named fields, whose fields are read from an object
+#[diagnostic::on_unimplemented(
+    message= "`{Self}` can't be flattened, it isn't a struct with named fields",
+    label= "the type of a #[schema(flatten)] field",
+)]
+pub trait SchemaFlatten: Schematize {
+}
+
+// Deserialize an array element or enum payload, which may be written as the `default` literal.
+// Struct fields handle the literal themselves, since they use the default of the enclosing struct.
+fn deserialize_or_default<T: Schematize>(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<T> {
+    match schema_value.kind {
+        SchemaValueKind::Default => Ok(T::schema_default()),
+        _ => T::deserialize(schema_value, context),
+    }
 }
 
 #[derive(Schematize, Debug)]
@@ -117,12 +439,15 @@ enum DataType {
 }
 
 #[derive(Schematize, Debug)]
+#[schema(unknown_fields = "collect")]
 struct InnerData {
     flag: bool,
     #[schema_default(type_enum=DataType::Secondary)]
+    #[schema(default)]
     type_enum: DataType,
 }
 
+#[allow(dead_code)]
 #[derive(Schematize, Debug)]
 struct Data {
     point: [i32; 3],
@@ -150,18 +475,22 @@ fn parse_test() {
     let args: Vec<String>= env::args().collect();
     if args.len() > 1 {
         let file_path= &args[1];
-        let block_definition= parser::load_definition::<ParserData>(&file_path);
+        let block_definition= parser::load_definition::<ParserData>(file_path);
 
         match block_definition {
             Ok(definition) => {
                 println!("Successfully loaded block definition '{}'", file_path);
+                for warning in definition.get_warnings() {
+                    println!("warning: {}", warning);
+                }
                 println!("{:?}", definition.get_definition());
 
                 println!("Serializing definition");
                 println!("\n{}", parser::serialize_definition(definition));
             },
-            Err(_) => {
-                println!("Failed to load block definition '{}'", file_path);
+            Err(errors) => {
+                println!("Failed to load block definition '{}' with {} error(s)", file_path, errors.len());
+                println!("{}", errors);
             }
         }
     } else {
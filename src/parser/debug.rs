@@ -1,15 +1,18 @@
-use crate::parser::tokens::{Token, Symbol};
+use crate::parser::tokens::{TokenKind, Symbol};
 
 use std::fmt;
 
-impl fmt::Display for Token {
+impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Token::Identifier(str) => write!(f, "{}", str),
-            Token::String(str) => write!(f, "\"{}\"", str),
-            Token::Integer(num) => write!(f, "{}", num),
-            Token::Decimal(num) => write!(f, "{}", num),
-            Token::Punctuation(symbol) => write!(f, "{:?}", symbol),
+            TokenKind::Identifier(str) => write!(f, "{}", str),
+            TokenKind::RawIdentifier(str) => write!(f, "r#{}", str),
+            TokenKind::String(str) => write!(f, "{:?}", str),
+            TokenKind::Integer(num) => write!(f, "{}", num),
+            TokenKind::Decimal(num) => write!(f, "{}", num),
+            TokenKind::Punctuation(symbol) => write!(f, "'{:?}'", symbol),
+            TokenKind::Invalid => write!(f, "invalid literal"),
+            TokenKind::EndOfFile => write!(f, "end of file"),
         }
     }
 }
@@ -28,53 +31,3 @@ impl fmt::Debug for Symbol {
         write!(f, "{}", char)
     }
 }
-
-fn print_newline(tabs: i32) {
-    println!();
-    for _ in 0..tabs {
-        print!("  ");
-    }
-}
-
-pub fn print_tokens(tokens: &Vec<Token>, error_index: usize) {
-    let mut tabs= 0;
-
-    println!("Parsing of schema failed:");
-    for index in 0..tokens.len() {
-        let token= &tokens[index];
-        match token {
-            Token::Identifier(_) => {
-                print_newline(tabs);
-            },
-            Token::Punctuation(symbol) => {
-                match symbol {
-                    Symbol::CloseCurlyBrace => {
-                        tabs-= 1;
-                        print_newline(tabs);
-                    },
-                    Symbol::OpenCurlyBrace => {
-                        tabs+= 1;
-                    }
-                    _ => (),
-                }
-            },
-            _ => (),
-        }
-
-        if index == error_index {
-            print!(" ERROR<< {:?} >> ", token);
-        } else {
-            print!(" {} ", token);
-        }
-
-        match token {
-            Token::Punctuation(symbol) => {
-                match symbol {
-                    Symbol::CloseCurlyBrace => println!(),
-                    _ => (),
-                }
-            },
-            _ => (),
-        }
-    }
-}
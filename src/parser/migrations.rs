@@ -0,0 +1,61 @@
+use crate::*;
+use super::schema::ParsedDefinition;
+
+use std::any;
+use std::collections;
+
+// Upgrades a definition from the previous version of its schema, e.g. by renaming or filling in fields.
+// The SchemaValue tree borrows its strings from the file contents, so it can't own a string. Any string
+// (or field name) added by a migration must borrow from 'static data, such as a string literal; one built
+// at runtime, e.g. with format!(), has to be leaked to live that long.
+pub type Migration= for<'a> fn(&mut SchemaValue<'a>) -> Result<(), SchemaError>;
+
+// Migrations for each schematized type, registered by the version they upgrade a definition to.
+#[derive(Clone, Debug, Default)]
+pub struct Migrations {
+    migrations: collections::HashMap<(&'static str, u32), Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Migrations {
+        Migrations::default()
+    }
+
+    // Register the migration which upgrades a definition of T from `version - 1` to `version`
+    pub fn register<T: Schematize>(&mut self, version: u32, migration: Migration) -> &mut Migrations {
+        self.migrations.insert((any::type_name::<T>(), version), migration);
+        self
+    }
+
+    fn get<T: Schematize>(&self, version: u32) -> Option<&Migration> {
+        self.migrations.get(&(any::type_name::<T>(), version))
+    }
+}
+
+// Bring a definition written with an older version of T's schema up to date, one version at a time.
+pub fn migrate<T: Schematize>(definition: &mut ParsedDefinition, migrations: &Migrations) -> SchemaResult<()> {
+    if definition.version > T::VERSION {
+        // Written by a newer build, there's no way to migrate it back
+        return Err(SchemaError::new(SchemaErrorKind::Version)
+            .with_type_name(any::type_name::<T>())
+            .with_expected(format!("version {} or older", T::VERSION))
+            .with_found(format!("version {}", definition.version))
+            .with_span(definition.version_span));
+    }
+
+    for version in definition.version + 1..=T::VERSION {
+        match migrations.get::<T>(version) {
+            Some(migration) => migration(&mut definition.schema_value)?,
+            None => {
+                return Err(SchemaError::new(SchemaErrorKind::Version)
+                    .with_type_name(any::type_name::<T>())
+                    .with_expected(format!("a migration from version {} to {}", version - 1, version))
+                    .with_found("none registered")
+                    .with_span(definition.version_span));
+            }
+        }
+    }
+
+    definition.version= T::VERSION;
+    Ok(())
+}
@@ -1,42 +1,160 @@
 mod tokens;
 mod schema;
 mod debug;
+mod migrations;
+
+pub use migrations::{Migration, Migrations};
 
 use crate::*;
 
 use std::marker;
+use std::ptr;
+use std::mem;
 use std::fs;
 use std::str;
 use std::alloc;
+use std::any;
+use std::error;
+use std::fmt;
+
+// A location in the source of a definition file. Lines and columns start at 1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub offset: usize,  // byte offset from the start of the file
+    pub line: usize,
+    pub column: usize,  // counted in characters, not bytes
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub span: Span,        // where the parser hit the error
+    pub expected: String,  // description of what the parser was looking for
+    pub found: String,     // description of what the parser found instead
+    pub related: Option<Span>, // a second location involved in the error, e.g. the first of two duplicate fields
+}
+
+impl ParseError {
+    pub fn new(span: Span, expected: impl Into<String>, found: impl Into<String>) -> ParseError {
+        ParseError {
+            span,
+            expected: expected.into(),
+            found: found.into(),
+            related: None,
+        }
+    }
+
+    pub fn with_related(mut self, related: Span) -> ParseError {
+        self.related= Some(related);
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: expected {}, found {}", self.span, self.expected, self.found)?;
+        if let Some(related)= self.related {
+            write!(f, " (see {})", related)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ParseError {}
 
-pub type ParseResult<T>= Result<T, &'static str>;
+pub type ParseResult<T>= Result<T, ParseError>;
+
+// How strictly separators between object fields and array elements are checked
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Grammar {
+    // Commas between object fields are optional, and a trailing comma is allowed in objects and arrays.
+    #[default]
+    Lenient,
+    // Object fields and array elements must be separated by exactly one comma, with no trailing comma.
+    Strict,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    pub grammar: Grammar,
+    // Upgrade definition files written with an older version of the schema, see #[schema(version = N)]
+    pub migrations: Migrations,
+}
 
 pub struct BlockDefinition<T> {
     // TODO: include tag name handle in here
     block_handle: block::BlockHandle<T>, // this CANNOT be null
+    layout: alloc::Layout,               // the layout the block was allocated with
+    initialized: bool,                   // whether the definition has been written to the block
+    warnings: Vec<SchemaError>,          // problems found while loading that didn't stop the definition from loading
+    unknown_fields: UnknownFields,       // kept by #[schema(unknown_fields = "collect")], written back out on serialize
     phantom: marker::PhantomData<T>
 }
 
-impl<'a, T> BlockDefinition<T> {
+impl<T> BlockDefinition<T> {
     pub fn get_block_handle(&self) -> &block::BlockHandle<T> {
         &self.block_handle
     }
 
-    pub fn get_definition(&self) -> &'a T {
+    pub fn get_warnings(&self) -> &[SchemaError] {
+        &self.warnings
+    }
+
+    pub fn get_unknown_fields(&self) -> &UnknownFields {
+        &self.unknown_fields
+    }
+
+    pub fn get_definition(&self) -> &T {
         assert!(!self.block_handle.is_null());
         unsafe { &*self.block_handle.get_pointer() }
     }
 
-    fn get_definition_mut(&self) -> &'a mut T {
-        assert!(!self.block_handle.is_null());
-        unsafe { &mut *self.block_handle.get_pointer_mut() }
+    // The block is uninitialized memory, so the definition is written without dropping what was there
+    fn write_definition(&mut self, definition: T) {
+        assert!(!self.block_handle.is_null() && !self.initialized);
+        unsafe { self.block_handle.get_pointer_mut().write(definition) }
+        self.initialized= true;
+    }
+}
+
+impl<T> Drop for BlockDefinition<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // The definition owns everything else in the block, e.g. its arrays drop their elements
+            if self.initialized {
+                ptr::drop_in_place(self.block_handle.get_pointer_mut());
+            }
+            block::deallocate_block(&self.block_handle, self.layout);
+        }
     }
 }
 
-fn build_definition<T: Schematize>(contents: &str) -> ParseResult<BlockDefinition<T>> {
-    // Parse the file contents into a schem value representation
-    let tokens= tokens::string_to_tokens(contents)?;
-    let schema_value= schema::tokens_to_schema_value(&tokens)?;
+fn build_definition<T: Schematize>(contents: &str, options: &LoadOptions) -> Result<BlockDefinition<T>, SchemaErrors> {
+    // Parse the file contents into a schema value representation
+    let (tokens, mut parse_errors)= tokens::string_to_tokens(contents);
+    let parsed_definition= schema::tokens_to_schema_value(&tokens, options.grammar);
+
+    // Don't deserialize a file with syntax errors, the values the parser skipped over would
+    // show up again as missing fields.
+    let mut parsed_definition= match parsed_definition {
+        Ok(parsed_definition) if parse_errors.is_empty() => parsed_definition,
+        result => {
+            if let Err(errors)= result {
+                parse_errors.extend(errors);
+            }
+            parse_errors.sort_by_key(|error| error.span.offset);
+            return Err(parse_errors.into_iter().map(SchemaError::from).collect());
+        }
+    };
+
+    // Bring a file written with an older version of the schema up to date
+    migrations::migrate::<T>(&mut parsed_definition, &options.migrations)?;
+    let schema_value= parsed_definition.schema_value;
 
     // TODO: Validity check of the structure, optionally tuning it up w/ default values, etc.
 
@@ -46,19 +164,23 @@ fn build_definition<T: Schematize>(contents: &str) -> ParseResult<BlockDefinitio
 
     // Recursively build the layout on the fields in this schematize type.
     // This is a no-op unless there are fields using dynamic memory (e.g. strings, vectors)
-    let layout_result= T::build_layout(&schema_value, layout, &mut layout_offsets);
-
-    let layout;
-    match layout_result {
-        Ok(built_layout) => {
-            layout= built_layout.pad_to_align();
-        },
-        Err(_) => return Err("Failed to build layout for definition."),
+    let layout= match T::build_layout(&schema_value, layout, &mut layout_offsets) {
+        Ok(built_layout) => built_layout.pad_to_align(),
+        Err(err) => {
+            return Err(SchemaError::new(SchemaErrorKind::Layout)
+                .with_type_name(any::type_name::<T>())
+                .with_found(err.to_string())
+                .into());
+        }
     };
 
     // Allocate the block memory
-    let block_definition= BlockDefinition {
+    let mut block_definition= BlockDefinition {
         block_handle: block::allocate_block(layout),
+        layout,
+        initialized: false,
+        warnings: Vec::new(),
+        unknown_fields: UnknownFields::new(),
         phantom: marker::PhantomData,
     };
 
@@ -75,38 +197,65 @@ fn build_definition<T: Schematize>(contents: &str) -> ParseResult<BlockDefinitio
         offsets: layout_offsets,
         offset_index: 0,
         path: Vec::new(),  // used for debug inspection
+        errors: Vec::new(),
+        warnings: Vec::new(),
+        unknown_fields: UnknownFields::new(),
+        flattened: false,
+        root_type: any::type_name::<T>(),
     };
 
-    // Deserialize the definition into the block memory
-    let result= T::deserialize(&schema_value, &mut deserialize_context);
-    match result {
-        Ok(deserialized_definition) => {
-            *block_definition.get_definition_mut()= deserialized_definition;
+    // Deserialize the definition into the block memory. Fields that fail to deserialize are
+    // recorded in the context, and deserializing carries on with the next field.
+    let deserialized_definition= T::deserialize(&schema_value, &mut deserialize_context);
+    let mut errors= deserialize_context.errors;
+    match deserialized_definition {
+        Ok(deserialized_definition) if errors.is_empty() => {
+            block_definition.write_definition(deserialized_definition);
+            block_definition.warnings= deserialize_context.warnings;
+            block_definition.unknown_fields= deserialize_context.unknown_fields;
             Ok(block_definition)
         },
-        Err(e) => {
-            println!("  SchemaError::{:?}", e);
-            Err("Failed to deserialize schema definition.")
+        result => {
+            if let Err(error)= result {
+                errors.push(error);
+            }
+            // Errors are found field by field, e.g. unknown fields before any known ones, so list them in file order
+            errors.sort_by_key(|error| error.span().map(|span| span.offset));
+            Err(errors.into_iter().collect())
         }
     }
 }
 
 // Reads, parses, and schematizes the the given definition file from disk
-pub fn load_definition<T: Schematize>(file_path: &str) -> Result<BlockDefinition<T>, &str> {
+// On failure, returns every error found in the file.
+pub fn load_definition<T: Schematize>(file_path: &str) -> Result<BlockDefinition<T>, SchemaErrors> {
+    load_definition_with_options(file_path, &LoadOptions::default())
+}
+
+pub fn load_definition_with_options<T: Schematize>(file_path: &str, options: &LoadOptions)
+    -> Result<BlockDefinition<T>, SchemaErrors> {
     // TODO:
     // - We should have a caching system so if a definition is requested multiple times,
     //   it can reuse the existing memory.
-    // - Input options about loading definitions, e.g. versioning markup on structs, or markup/commands
-    //   to force deserialize an object into its default schema values if it hits an error.
-
+    // - Input options about loading definitions, e.g. markup/commands to force deserialize an object
+    //   into its default schema values if it hits an error.
 
     let file_contents= fs::read_to_string(file_path);
     match file_contents {
-        Ok(file_contents) =>
-            build_definition(&file_contents),
+        Ok(file_contents) => match build_definition(&file_contents, options) {
+            Ok(mut block_definition) => {
+                block_definition.warnings= mem::take(&mut block_definition.warnings).into_iter()
+                    .map(|warning| warning.with_file(file_path))
+                    .collect();
+                Ok(block_definition)
+            },
+            Err(errors) => Err(errors.with_file(file_path)),
+        },
         Err(err) => {
-            println!("Failed to read file contents '{}'.\n Error: {}", file_path, err);
-            Err("Failed to read file contents.")
+            Err(SchemaError::new(SchemaErrorKind::Io)
+                .with_file(file_path)
+                .with_found(err.to_string())
+                .into())
         }
     }
 }
@@ -114,13 +263,1057 @@ pub fn load_definition<T: Schematize>(file_path: &str) -> Result<BlockDefinition
 // Given a schematized object, write its definition file to disk.
 pub fn serialize_definition<T: Schematize>(object: BlockDefinition<T>) -> String {
     // Serialize the block into a formatted string
-    let mut serialize_context= SerializeContext {
-        string: String::new(),
-        tabs: 0,
-    };
+    let mut serialize_context= SerializeContext::new();
+    serialize_context.unknown_fields= object.unknown_fields.clone();
+
+    // Stamp the version of the schema, so the file can be migrated if the schema changes
+    if T::VERSION > 0 {
+        serialize_context.print(&format!("version {}", T::VERSION));
+        serialize_context.println();
+    }
     object.get_definition().serialize(&mut serialize_context);
 
     serialize_context.string
 
     //fs::write(file_path, serialize_context.string.as_bytes()).expect("Unable to write file.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schema_macros::Schematize;
+
+    fn load<T: Schematize>(contents: &str) -> BlockDefinition<T> {
+        build_definition::<T>(contents, &LoadOptions::default()).unwrap_or_else(|errors| panic!("{}", errors))
+    }
+
+    // The errors for loading `contents`, formatted as "line:column: kind at 'path' ..."
+    fn load_errors<T: Schematize + fmt::Debug>(contents: &str) -> Vec<String> {
+        match build_definition::<T>(contents, &LoadOptions::default()) {
+            Ok(definition) => panic!("loaded {:?}", definition.get_definition()),
+            Err(errors) => errors.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    // Serializes the definition, checking that it loads back to the same text
+    fn round_trip<T: Schematize>(definition: BlockDefinition<T>) -> String {
+        let serialized= serialize_definition(definition);
+        assert_eq!(serialize_definition(load::<T>(&serialized)), serialized);
+        serialized
+    }
+
+    #[derive(Schematize)]
+    struct Wide {
+        values: SchemaArray<i64>,
+        names: SchemaArray<SchemaString>,
+    }
+
+    // Array elements are laid out in the block at byte offsets, whatever the size of the element
+    #[test]
+    fn array_of_wide_elements() {
+        let definition= build_definition::<Wide>("{ values: [1, 2, 3], names: [\"a\", \"bc\"] }", &LoadOptions::default())
+            .unwrap_or_else(|errors| panic!("{}", errors));
+        let values= definition.get_definition().values.as_slice().unwrap();
+        assert_eq!(values, [1, 2, 3]);
+
+        // The first array follows the definition itself
+        let block_start= definition.block_handle.get_pointer() as usize;
+        assert_eq!(values.as_ptr() as usize - block_start, std::mem::size_of::<Wide>());
+
+        let names= definition.get_definition().names.as_slice().unwrap();
+        assert_eq!(names.iter().map(|name| name.as_str().unwrap()).collect::<Vec<_>>(), ["a", "bc"]);
+    }
+
+    // A skipped field can own heap memory, e.g. a runtime cache
+    #[repr(C)]
+    #[derive(Schematize)]
+    struct Cached {
+        a: [i64; 4],
+        #[schema(skip = "Vec::new()")]
+        cache: Vec<String>,
+    }
+
+    #[derive(Schematize)]
+    struct CachedArray {
+        items: SchemaArray<Cached>,
+    }
+
+    // Reads a string value into a std String, which owns heap memory outside of the block
+    mod owned_string {
+        use crate::*;
+        use std::alloc;
+
+        pub fn schema_default() -> String {
+            String::new()
+        }
+
+        pub fn serialize(value: &str, context: &mut SerializeContext) {
+            schema_string::serialize_str(value, context);
+        }
+
+        pub fn build_layout(_schema_value: &SchemaValue, layout: alloc::Layout, _offsets: &mut Vec<usize>)
+            -> Result<alloc::Layout, alloc::LayoutError> {
+            Ok(layout)
+        }
+
+        pub fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<String> {
+            match schema_value.kind {
+                SchemaValueKind::String(str) => Ok(String::from(str)),
+                _ => Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value).with_expected("String")),
+            }
+        }
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Named {
+        id: i32,
+        #[schema(with = owned_string)]
+        name: String,
+    }
+
+    #[derive(Schematize)]
+    struct NamedArray {
+        items: SchemaArray<Named>,
+    }
+
+    #[test]
+    fn codec_field_with_drop_glue() {
+        let definition= build_definition::<Named>("{ id: 1, name: \"first\" }", &LoadOptions::default())
+            .unwrap_or_else(|errors| panic!("{}", errors));
+        assert_eq!(definition.get_definition().name, "first");
+
+        let definition= build_definition::<NamedArray>(
+            "{ items: [{ id: 1, name: \"first\" }, { id: 2, name: \"second\" }] }", &LoadOptions::default())
+            .unwrap_or_else(|errors| panic!("{}", errors));
+        let items= definition.get_definition().items.as_slice().unwrap();
+        assert_eq!(items[1].id, 2);
+        assert_eq!(items[1].name, "second");
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Pair {
+        a: i32,
+        b: i32,
+    }
+
+    #[test]
+    fn errors_in_file_order() {
+        let errors= match build_definition::<Pair>("{ a: 1.5, b: \"x\", c: 1 }", &LoadOptions::default()) {
+            Ok(definition) => panic!("loaded {:?}", definition.get_definition()),
+            Err(errors) => errors,
+        };
+        let spans= errors.iter().map(|error| error.span().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(spans, ["1:6", "1:14", "1:19"]);
+
+        // Fields are deserialized in declaration order, so `a` is reported first unless sorted
+        let errors= match build_definition::<Pair>("{ b: \"x\", a: 1.5 }", &LoadOptions::default()) {
+            Ok(definition) => panic!("loaded {:?}", definition.get_definition()),
+            Err(errors) => errors,
+        };
+        let spans= errors.iter().map(|error| error.span().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(spans, ["1:6", "1:14"]);
+
+        // Tokenizer errors are collected before the parser runs
+        let errors= match build_definition::<Pair>("{ a: , b: 1.2.3 }", &LoadOptions::default()) {
+            Ok(definition) => panic!("loaded {:?}", definition.get_definition()),
+            Err(errors) => errors,
+        };
+        let spans= errors.iter().map(|error| error.span().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(spans, ["1:6", "1:14"]);
+    }
+
+    #[test]
+    fn skipped_field_with_drop_glue() {
+        let definition= build_definition::<Cached>("{ a: [1, 2, 3, 4] }", &LoadOptions::default())
+            .unwrap_or_else(|errors| panic!("{}", errors));
+        assert_eq!(definition.get_definition().a, [1, 2, 3, 4]);
+        assert!(definition.get_definition().cache.is_empty());
+
+        let definition= build_definition::<CachedArray>(
+            "{ items: [{ a: [1, 2, 3, 4] }, { a: [5, 6, 7, 8] }] }", &LoadOptions::default())
+            .unwrap_or_else(|errors| panic!("{}", errors));
+        let items= definition.get_definition().items.as_slice().unwrap();
+        assert_eq!(items[1].a, [5, 6, 7, 8]);
+        assert!(items.iter().all(|item| item.cache.is_empty()));
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Speeds {
+        walk: f32,
+        #[schema(default)]
+        #[schema_default(run=5.0)]
+        run: f32,
+        #[schema(default)]
+        jump: i32,
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(default)]
+    #[allow(dead_code)]
+    struct AllOptional {
+        #[schema_default(count=3)]
+        count: i32,
+        name: SchemaString,
+        speeds: SchemaArray<i32>,
+    }
+
+    #[test]
+    fn missing_optional_fields() {
+        let definition= load::<Speeds>("{ walk: 1.5 }");
+        let speeds= definition.get_definition();
+        assert_eq!((speeds.walk, speeds.run, speeds.jump), (1.5, 5.0, 0));
+
+        let definition= load::<AllOptional>("{}");
+        assert_eq!(definition.get_definition().count, 3);
+        assert_eq!(definition.get_definition().name.as_str(), None);
+
+        // Defaults are written back out, so the file no longer depends on them
+        assert_eq!(round_trip(load::<Speeds>("{ walk: 1.5 }")), "{\n  walk: 1.5,\n  run: 5.0,\n  jump: 0\n}");
+
+        assert_eq!(load_errors::<Speeds>("{ run: 2.0 }"), ["1:1: missing field at 'walk' while deserializing Speeds"]);
+    }
+
+    #[test]
+    fn default_literal() {
+        // Uses the schema_default() of the struct, so #[schema_default(...)] markup applies to required fields too
+        let definition= load::<Speeds>("{ walk: default, run: default, jump: 2 }");
+        let speeds= definition.get_definition();
+        assert_eq!((speeds.walk, speeds.run, speeds.jump), (0.0, 5.0, 2));
+
+        let definition= load::<AllOptional>("{ count: default, speeds: [1, default, 3] }");
+        assert_eq!(definition.get_definition().count, 3);
+        assert_eq!(definition.get_definition().speeds.as_slice().unwrap(), [1, 0, 3]);
+
+        assert_eq!(load_errors::<Speeds>("{ walk: defaults }"),
+            ["1:9: wrong value at 'walk' while deserializing f32: expected Decimal, found enum variant defaults"]);
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(unknown_fields = "warn")]
+    struct Lenient {
+        a: i32,
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(unknown_fields = "collect")]
+    struct Collected {
+        a: i32,
+        inner: CollectedInner,
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(unknown_fields = "collect")]
+    struct CollectedInner {
+        b: bool,
+    }
+
+    #[test]
+    fn unknown_fields() {
+        assert_eq!(load_errors::<Pair>("{ a: 1, x: 2, b: 3, y: 4 }"), [
+            "1:9: unknown field at 'x' while deserializing Pair: expected one of: a, b, found 'x'",
+            "1:21: unknown field at 'y' while deserializing Pair: expected one of: a, b, found 'y'",
+        ]);
+
+        let definition= load::<Lenient>("{ a: 1, x: 2, y: [3] }");
+        assert_eq!(definition.get_definition().a, 1);
+        let warnings= definition.get_warnings().iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(warnings, [
+            "1:9: unknown field at 'x' while deserializing Lenient: expected one of: a, found 'x'",
+            "1:15: unknown field at 'y' while deserializing Lenient: expected one of: a, found 'y'",
+        ]);
+        assert_eq!(serialize_definition(definition), "{\n  a: 1\n}");
+    }
+
+    #[test]
+    fn collected_unknown_fields() {
+        let definition= load::<Collected>("{ a: 1, x: [1, 2], inner: { b: true, y: { z: \"s\" } } }");
+        assert!(definition.get_warnings().is_empty());
+        let unknown_fields= definition.get_unknown_fields();
+        assert_eq!(unknown_fields[""].iter().map(|field| field.name.as_str()).collect::<Vec<_>>(), ["x"]);
+        assert_eq!(unknown_fields["inner"].iter().map(|field| field.name.as_str()).collect::<Vec<_>>(), ["y"]);
+
+        // Written back out at the end of the object they were found in
+        assert_eq!(round_trip(definition), "\
+{
+  a: 1,
+  inner: {
+    b: true,
+    y: {
+      z: \"s\"
+    }
+  },
+  x: [
+    1,
+    2
+  ]
+}");
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Renamed {
+        #[schema(rename = "type", alias = "kind", alias = "class")]
+        item_type: Shape,
+        r#loop: bool,
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Shape {
+        #[schema(rename = "Ball", alias = "Circle")]
+        Sphere,
+        #[schema(alias = "Square")]
+        Cube { #[schema(rename = "edge")] size: i32 },
+    }
+
+    #[test]
+    fn renamed_fields_and_variants() {
+        let definition= load::<Renamed>("{ type: Ball, loop: true }");
+        assert_eq!(definition.get_definition().item_type, Shape::Sphere);
+        assert_eq!(round_trip(definition), "{\n  type: Ball,\n  loop: true\n}");
+
+        // Read by their aliases, but written under their schema name
+        let definition= load::<Renamed>("{ class: Square { edge: 2 }, loop: false }");
+        assert_eq!(definition.get_definition().item_type, Shape::Cube { size: 2 });
+        assert_eq!(serialize_definition(definition), "{\n  type: Cube {\n    edge: 2\n  },\n  loop: false\n}");
+        assert_eq!(load::<Renamed>("{ kind: Circle, loop: true }").get_definition().item_type, Shape::Sphere);
+
+        // The Rust names are no longer read
+        assert_eq!(load_errors::<Renamed>("{ item_type: Sphere, loop: true }"), [
+            "1:1: missing field at 'type' while deserializing Renamed",
+            "1:3: unknown field at 'item_type' while deserializing Renamed: expected one of: type, kind, class, loop, \
+             found 'item_type'",
+        ]);
+        assert_eq!(load_errors::<Renamed>("{ type: Sphere, loop: true }"),
+            ["1:9: unknown identifier at 'type' while deserializing Shape: expected a variant of Shape, found enum variant Sphere"]);
+    }
+
+    #[test]
+    fn renamed_field_written_twice() {
+        assert_eq!(load_errors::<Renamed>("{ type: Ball, loop: true, kind: Square { edge: 1 } }"), [
+            "1:27: duplicate field at 'type' while deserializing Renamed: expected one of: type, kind, class, \
+             found 'type' and 'kind' (see 1:3)",
+        ]);
+    }
+
+    static SCHEMA_DEFAULTS: std::sync::atomic::AtomicUsize= std::sync::atomic::AtomicUsize::new(0);
+
+    fn count_schema_default() -> usize {
+        SCHEMA_DEFAULTS.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Skipped {
+        #[schema(default)]
+        a: i32,
+        #[schema(default)]
+        b: i32,
+        #[schema(skip = "count_schema_default()")]
+        schema_defaults: usize,
+        #[schema(skip)]
+        #[schema_default(cached=7)]
+        cached: i32,
+    }
+
+    #[test]
+    fn skipped_fields() {
+        // Skipped and missing fields all come from a single schema_default()
+        let definition= load::<Skipped>("{ b: default }");
+        let skipped= definition.get_definition();
+        assert_eq!((skipped.a, skipped.b), (0, 0));
+        assert_eq!(skipped.schema_defaults, 1);
+        assert_eq!(skipped.cached, 7);
+
+        assert_eq!(serialize_definition(definition), "{\n  a: 0,\n  b: 0\n}");
+        assert_eq!(load_errors::<Skipped>("{ a: 1, cached: 2 }"),
+            ["1:9: unknown field at 'cached' while deserializing Skipped: expected one of: a, b, found 'cached'"]);
+    }
+
+    thread_local! {
+        static TRACKER: std::rc::Rc<()>= std::rc::Rc::new(());
+    }
+
+    fn tracker_count() -> usize {
+        TRACKER.with(std::rc::Rc::strong_count)
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Tracked {
+        a: i32,
+        #[schema(skip = "TRACKER.with(std::rc::Rc::clone)")]
+        tracker: std::rc::Rc<()>,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct TrackedArrays {
+        tracked: Tracked,
+        items: SchemaArray<Tracked>,
+        nested: SchemaArray<SchemaArray<Tracked>>,
+    }
+
+    // Values with drop glue in the block are dropped along with the definition
+    #[test]
+    fn definition_drops_block_contents() {
+        let definition= load::<TrackedArrays>("{ tracked: { a: 1 }, items: [{ a: 2 }, { a: 3 }], nested: [[{ a: 4 }], []] }");
+        assert_eq!(tracker_count(), 5);
+        drop(definition);
+        assert_eq!(tracker_count(), 1);
+
+        // Including the values which were deserialized before an error
+        load_errors::<TrackedArrays>("{ tracked: { a: 1 }, items: [{ a: 2 }, { a: 3.5 }], nested: [] }");
+        assert_eq!(tracker_count(), 1);
+    }
+
+    // Version 1 had a single `size`, which version 2 split into `width` and `height`
+    #[derive(Schematize, Debug)]
+    #[schema(version = 2)]
+    struct Versioned {
+        name: SchemaString,
+        width: i32,
+        height: i32,
+    }
+
+    fn split_size(schema_value: &mut SchemaValue) -> Result<(), SchemaError> {
+        let fields_map= match &mut schema_value.kind {
+            SchemaValueKind::Object(fields_map) => fields_map,
+            _ => return Ok(()),
+        };
+        // The new fields point at the old one, for any errors
+        let (size, span, name_span)= match fields_map.remove("size") {
+            Some(SchemaField { value: SchemaValue { kind: SchemaValueKind::Integer(size), span }, name_span, .. }) =>
+                (size, span, name_span),
+            _ => return Err(SchemaError::new(SchemaErrorKind::MissingField).with_path(String::from("size"))),
+        };
+        fields_map.insert("width", name_span, SchemaValue { kind: SchemaValueKind::Integer(size), span });
+        fields_map.insert("height", name_span, SchemaValue { kind: SchemaValueKind::Integer(size), span });
+        Ok(())
+    }
+
+    fn add_name(schema_value: &mut SchemaValue) -> Result<(), SchemaError> {
+        if let SchemaValueKind::Object(fields_map)= &mut schema_value.kind {
+            let span= schema_value.span;
+            fields_map.insert("name", span, SchemaValue { kind: SchemaValueKind::String("unnamed"), span });
+        }
+        Ok(())
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Outer {
+        inner: Versioned,
+    }
+
+    #[test]
+    fn migrations() {
+        let mut options= LoadOptions::default();
+        options.migrations.register::<Versioned>(1, add_name).register::<Versioned>(2, split_size);
+        let load_versioned= |contents: &str| build_definition::<Versioned>(contents, &options);
+
+        // A file without a header is version 0, and runs every migration
+        let definition= load_versioned("{ size: 3 }").unwrap_or_else(|errors| panic!("{}", errors));
+        let versioned= definition.get_definition();
+        assert_eq!((versioned.name.as_str(), versioned.width, versioned.height), (Some("unnamed"), 3, 3));
+
+        // Saved with the current version, which loads without any migrations
+        let definition= load_versioned("version 1 { name: \"box\", size: 2 }").unwrap_or_else(|errors| panic!("{}", errors));
+        let serialized= serialize_definition(definition);
+        assert_eq!(serialized, "version 2\n{\n  name: \"box\",\n  width: 2,\n  height: 2\n}");
+        assert_eq!(load::<Versioned>(&serialized).get_definition().width, 2);
+
+        let errors= |result: Result<BlockDefinition<Versioned>, SchemaErrors>| match result {
+            Ok(definition) => panic!("loaded {:?}", definition.get_definition()),
+            Err(errors) => errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        };
+        assert_eq!(errors(load_versioned("version 3 { name: \"box\", width: 1, height: 1 }")), [
+            "1:9: unsupported version while deserializing rust_schema::parser::tests::Versioned: \
+             expected version 2 or older, found version 3",
+        ]);
+        assert_eq!(errors(build_definition::<Versioned>("version 1 { name: \"box\", size: 2 }", &LoadOptions::default())), [
+            "1:9: unsupported version while deserializing rust_schema::parser::tests::Versioned: \
+             expected a migration from version 1 to 2, found none registered",
+        ]);
+    }
+
+    #[test]
+    fn nested_version() {
+        assert_eq!(load_errors::<Outer>("{ inner: { name: \"box\", width: 1, height: 1 } }"), [
+            "1:10: unsupported version at 'inner' while deserializing rust_schema::parser::tests::Versioned: \
+             expected version 2 to only be set on the root type of a definition file, rust_schema::parser::tests::Outer, \
+             found a nested value",
+        ]);
+    }
+
+    fn is_power_of_two(value: &i32) -> Result<(), String> {
+        match value.count_ones() {
+            1 => Ok(()),
+            _ => Err(format!("{} is not a power of two", value)),
+        }
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Validated {
+        #[schema(range(min = 0.0, max = 1.0))]
+        opacity: f32,
+        #[schema(range(min = 1))]
+        count: i32,
+        #[schema(len(min = 1, max = 3), non_empty)]
+        tags: SchemaArray<SchemaString>,
+        #[schema(non_empty, len(max = 4))]
+        name: SchemaString,
+        #[schema(validate = is_power_of_two)]
+        size: i32,
+        inner: SchemaArray<ValidatedInner>,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct ValidatedInner {
+        #[schema(range(max = 10))]
+        level: i32,
+    }
+
+    #[test]
+    fn validation() {
+        let definition= load::<Validated>(
+            "{ opacity: 1.0, count: 1, tags: [\"a\"], name: \"abcd\", size: 8, inner: [{ level: 10 }] }");
+        assert_eq!(definition.get_definition().size, 8);
+
+        // Every failure is reported, with the path of the field
+        assert_eq!(load_errors::<Validated>(
+            "{ opacity: 1.5, count: 0, tags: [], name: \"\", size: 6, inner: [{ level: 1 }, { level: 11 }] }"), [
+            "1:12: validation failed at 'opacity' while deserializing Validated: expected a value between 0 and 1, \
+             found decimal 1.5",
+            "1:24: validation failed at 'count' while deserializing Validated: expected a value of at least 1, found integer 0",
+            "1:33: validation failed at 'tags' while deserializing Validated: expected a length between 1 and 3, found length 0",
+            "1:33: validation failed at 'tags' while deserializing Validated: expected a non-empty value, \
+             found array of 0 element(s)",
+            "1:43: validation failed at 'name' while deserializing Validated: expected a non-empty value, found string \"\"",
+            "1:53: validation failed at 'size' while deserializing Validated: 6 is not a power of two",
+            "1:87: validation failed at 'inner[1].level' while deserializing ValidatedInner: expected a value of at most 10, \
+             found integer 11",
+        ]);
+        assert_eq!(load_errors::<Validated>(
+            "{ opacity: 0.5, count: 1, tags: [\"a\", \"b\", \"c\", \"d\"], name: \"abcde\", size: 1, inner: [] }"), [
+            "1:33: validation failed at 'tags' while deserializing Validated: expected a length between 1 and 3, found length 4",
+            "1:61: validation failed at 'name' while deserializing Validated: expected a length of at most 4, found length 5",
+        ]);
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(post_load = check_bounds)]
+    struct Bounded {
+        min: i32,
+        max: i32,
+        #[schema(skip)]
+        span: i32,  // computed on load
+    }
+
+    fn check_bounds(bounded: &mut Bounded) -> Result<(), SchemaErrors> {
+        if bounded.min > bounded.max {
+            return Err(SchemaError::new(SchemaErrorKind::Validation)
+                .with_path(String::from("max"))
+                .with_expected(format!("a max of at least min {}", bounded.min))
+                .with_found(bounded.max.to_string())
+                .into());
+        }
+        bounded.span= bounded.max - bounded.min;
+        Ok(())
+    }
+
+    #[derive(Schematize, Debug)]
+    #[schema(post_load = check_selection)]
+    struct Selection {
+        bounds: SchemaArray<Bounded>,
+        selected: u32,
+    }
+
+    // Runs after the hooks of its fields, so the spans are already computed
+    fn check_selection(selection: &mut Selection) -> Result<(), SchemaErrors> {
+        let bounds= selection.bounds.as_slice().unwrap_or(&[]);
+        match bounds.get(selection.selected as usize) {
+            Some(bounded) if bounded.span > 0 => Ok(()),
+            Some(_) => Err(SchemaError::new(SchemaErrorKind::Validation)
+                .with_path(format!("bounds[{}]", selection.selected))
+                .with_found("an empty selection")
+                .into()),
+            None => Err(SchemaError::new(SchemaErrorKind::Validation)
+                .with_path(String::from("selected"))
+                .with_expected(format!("an index less than {}", bounds.len()))
+                .with_found(selection.selected.to_string())
+                .into()),
+        }
+    }
+
+    #[test]
+    fn post_load_hooks() {
+        let definition= load::<Selection>("{ bounds: [{ min: 1, max: 4 }, { min: -2, max: 2 }], selected: 1 }");
+        let bounds= definition.get_definition().bounds.as_slice().unwrap();
+        assert_eq!(bounds.iter().map(|bounded| bounded.span).collect::<Vec<_>>(), [3, 4]);
+
+        // Paths are relative to the value the hook ran on
+        assert_eq!(load_errors::<Selection>("{ bounds: [{ min: 1, max: 4 }], selected: 1 }"),
+            ["1:43: validation failed at 'selected' while deserializing Selection: expected an index less than 1, found 1"]);
+        assert_eq!(load_errors::<Selection>("{ bounds: [{ min: 1, max: 1 }], selected: 0 }"),
+            ["1:11: validation failed at 'bounds[0]' while deserializing Selection: an empty selection"]);
+
+        // A hook doesn't run on a value with errors, so only the innermost one is reported
+        assert_eq!(load_errors::<Selection>("{ bounds: [{ min: 1, max: 4 }, { min: 5, max: 4 }], selected: 1 }"),
+            ["1:47: validation failed at 'bounds[1].max' while deserializing Bounded: expected a max of at least min 5, found 4"]);
+    }
+
+    #[test]
+    fn codec_round_trip() {
+        let definition= load::<Named>("{ id: 1, name: \"a \\\"quoted\\\" name\" }");
+        assert_eq!(definition.get_definition().name, "a \"quoted\" name");
+        assert_eq!(round_trip(definition), "{\n  id: 1,\n  name: \"a \\\"quoted\\\" name\"\n}");
+
+        // The codec's error is reported at the field, and its default takes the field's place
+        assert_eq!(load_errors::<Named>("{ id: 1, name: 2 }"), ["1:16: wrong value at 'name': expected String, found integer 2"]);
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Transform {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Sprite {
+        name: SchemaString,
+        #[schema(flatten)]
+        transform: Transform,
+        #[schema(flatten)]
+        scale: Scale,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Scale {
+        #[schema(default, alias = "scale")]
+        factor: i32,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Marker {
+        #[schema(flatten)]
+        position: Transform,
+        #[schema(flatten)]
+        offset: Transform,
+    }
+
+    #[test]
+    fn flattened_fields() {
+        let definition= load::<Sprite>("{ name: \"tree\", x: 1, y: 2, scale: 3 }");
+        let sprite= definition.get_definition();
+        assert_eq!((sprite.transform.x, sprite.transform.y, sprite.scale.factor), (1, 2, 3));
+        assert_eq!(round_trip(definition), "{\n  name: \"tree\",\n  x: 1,\n  y: 2,\n  factor: 3\n}");
+
+        // Unknown fields are checked against the fields of every flattened struct
+        assert_eq!(load_errors::<Sprite>("{ name: \"tree\", x: 1, y: 2, z: 3 }"), [
+            "1:29: unknown field at 'z' while deserializing Sprite: expected one of: name, x, y, factor, scale, found 'z'",
+        ]);
+        assert_eq!(load_errors::<Sprite>("{ name: \"tree\", y: 2 }"), ["1:1: missing field at 'x' while deserializing Transform"]);
+    }
+
+    #[test]
+    fn flattened_field_collision() {
+        // Both flattened structs have an x and a y, which can't be told apart in the shared object
+        assert_eq!(load_errors::<Marker>("{ x: 1, y: 2 }"), [
+            "1:1: duplicate field while deserializing Marker: expected unique field names across flattened fields, \
+             found more than one field named 'x'",
+        ]);
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Position(#[schema(range(min = -100.0))] f32, #[schema_default(1=5.0)] f32);
+
+    #[derive(Schematize, Debug)]
+    struct Flag;
+
+    #[derive(Schematize, Debug)]
+    #[schema(transparent)]
+    struct Meters(#[schema(range(min = 0.0))] f32);
+
+    #[derive(Schematize, Debug)]
+    #[schema(transparent)]
+    struct Id {
+        id: i32,
+        #[schema(skip = "std::marker::PhantomData")]
+        marker: std::marker::PhantomData<i32>,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Placed {
+        position: Position,
+        flag: Flag,
+        distance: Meters,
+        id: Id,
+    }
+
+    #[test]
+    fn tuple_unit_and_transparent_structs() {
+        let definition= load::<Placed>("{ position: [1.5, -2.0], flag: {}, distance: 3.5, id: 7 }");
+        let placed= definition.get_definition();
+        assert_eq!((placed.position.0, placed.position.1, placed.distance.0, placed.id.id), (1.5, -2.0, 3.5, 7));
+        assert_eq!(round_trip(definition), "\
+{
+  position: [
+    1.5,
+    -2.0
+  ],
+  flag: {},
+  distance: 3.5,
+  id: 7
+}");
+
+        // The `default` literal takes the default of the tuple struct, including any schema_default markup
+        let definition= load::<Placed>("{ position: [default, default], flag: {}, distance: 0.0, id: 0 }");
+        assert_eq!((definition.get_definition().position.0, definition.get_definition().position.1), (0.0, 5.0));
+
+        assert_eq!(load_errors::<Placed>("{ position: [1.0], flag: { a: 1 }, distance: -1.0, id: 1.5 }"), [
+            "1:13: wrong sized array at 'position' while deserializing Position: expected array of 2 element(s), \
+             found array of 1 element(s)",
+            "1:28: unknown field at 'flag.a' while deserializing Flag: expected no fields, found 'a'",
+            "1:46: validation failed at 'distance' while deserializing Meters: expected a value of at least 0, \
+             found decimal -1.0",
+            "1:56: wrong value at 'id' while deserializing i32: expected Integer, found decimal 1.5",
+        ]);
+        assert_eq!(load_errors::<Placed>("{ position: [-101.0, 0.0], flag: {}, distance: 1.0, id: 1 }"), [
+            "1:14: validation failed at 'position[0]' while deserializing Position: expected a value of at least -100, \
+             found decimal -101.0",
+        ]);
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    struct Span<T> {
+        min: T,
+        max: T,
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Curve<T> {
+        Constant(T),
+        Linear { from: T, to: T },
+    }
+
+    // Only names the kind of a Measure, so it isn't Schematize
+    #[derive(Debug)]
+    struct Celsius;
+
+    #[derive(Schematize, Debug)]
+    #[schema(bound = "")]
+    struct Measure<'a, K> {
+        value: f32,
+        #[schema(skip = "\"celsius\"")]
+        label: &'a str,
+        #[schema(skip = "std::marker::PhantomData")]
+        kind: std::marker::PhantomData<K>,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Ranges {
+        speed: Span<f32>,
+        counts: Span<Span<i32>>,
+        falloff: Curve<f32>,
+        heat: Measure<'static, Celsius>,
+    }
+
+    #[test]
+    fn generic_structs_and_enums() {
+        let definition= load::<Ranges>("{
+            speed: { min: 1.0, max: 2.5 },
+            counts: { min: { min: 1, max: 2 }, max: { min: 3, max: 4 } },
+            falloff: Linear { from: 1.0, to: 0.0 },
+            heat: { value: 20.5 }
+        }");
+        let ranges= definition.get_definition();
+        assert_eq!(ranges.speed, Span { min: 1.0, max: 2.5 });
+        assert_eq!(ranges.counts.max, Span { min: 3, max: 4 });
+        assert_eq!(ranges.falloff, Curve::Linear { from: 1.0, to: 0.0 });
+        assert_eq!((ranges.heat.value, ranges.heat.label), (20.5, "celsius"));
+        assert_eq!(round_trip(definition), "\
+{
+  speed: {
+    min: 1.0,
+    max: 2.5
+  },
+  counts: {
+    min: {
+      min: 1,
+      max: 2
+    },
+    max: {
+      min: 3,
+      max: 4
+    }
+  },
+  falloff: Linear {
+    from: 1.0,
+    to: 0.0
+  },
+  heat: {
+    value: 20.5
+  }
+}");
+
+        // Errors name the field of the generic type, and the type it was deserialized as
+        assert_eq!(load_errors::<Ranges>("{
+            speed: { min: 1, max: 2.5 },
+            counts: { min: { min: 1, max: 2.0 }, max: { min: 3, max: 4 } },
+            falloff: Constant { true },
+            heat: { value: 20.5, label: \"kelvin\" }
+        }"), [
+            "3:43: wrong value at 'counts.min.max' while deserializing i32: expected Integer, found decimal 2.0",
+            "4:33: wrong value at 'falloff' while deserializing f32: expected Decimal, found bool true",
+            "5:34: unknown field at 'heat.label' while deserializing Measure: expected one of: value, found 'label'",
+        ]);
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum DamageType {
+        Fire,
+        Ice,
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Event {
+        Clear,
+        Damage(f32, DamageType),
+        Spawn { count: i32, #[schema(default)] delay: f32 },
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Timeline {
+        events: SchemaArray<Event>,
+    }
+
+    #[test]
+    fn multi_field_and_named_variants() {
+        let definition= load::<Timeline>("{ events: [Damage { 2.5, Ice }, Spawn { count: 3, delay: 0.5 }, Spawn { count: 1 }, Clear] }");
+        assert_eq!(definition.get_definition().events.as_slice().unwrap(), [
+            Event::Damage(2.5, DamageType::Ice),
+            Event::Spawn { count: 3, delay: 0.5 },
+            Event::Spawn { count: 1, delay: 0.0 },
+            Event::Clear,
+        ]);
+        assert_eq!(round_trip(definition), "\
+{
+  events: [
+    Damage {
+      2.5,
+      Ice
+    },
+    Spawn {
+      count: 3,
+      delay: 0.5
+    },
+    Spawn {
+      count: 1,
+      delay: 0.0
+    },
+    Clear
+  ]
+}");
+
+        // A tuple variant's fields are read like array elements, and a struct-like variant's like struct fields
+        assert_eq!(load_errors::<Timeline>("{ events: [Damage { 2.5 }, Damage { true, Ice }, Damage { 1.0, Fire, Ice }, \
+            Spawn { delay: 1.0, size: 2 }, Spawn { 1, 2 }] }"), [
+            "1:21: wrong value at 'events[0]' while deserializing Event: expected 2 values, found decimal 2.5",
+            "1:37: wrong value at 'events[1][0]' while deserializing f32: expected Decimal, found bool true",
+            "1:57: wrong sized array at 'events[2]' while deserializing Event: expected 2 values, found array of 3 element(s)",
+            "1:83: missing field at 'events[3].count' while deserializing Event",
+            "1:97: unknown field at 'events[3].size' while deserializing Event: expected one of: count, delay, found 'size'",
+            "1:114: wrong value at 'events[4]' while deserializing Event: expected Object, found array of 2 element(s)",
+        ]);
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Weather {
+        Clear,
+        #[schema_default(intensity = 0.5)]
+        Rain { intensity: f32, thunder: bool },
+        Fog(f32),
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Wind {
+        Calm,
+        #[schema_default(1 = 2.5)]
+        Gust(i32, f32),
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Forecast {
+        #[schema(default)]
+        weather: Weather,
+        #[schema(default)]
+        wind: Wind,
+    }
+
+    #[test]
+    fn default_variants() {
+        // The marked variant is the default, not the first, with its payload's markup applied
+        let definition= load::<Forecast>("{}");
+        assert_eq!(definition.get_definition().weather, Weather::Rain { intensity: 0.5, thunder: false });
+        assert_eq!(definition.get_definition().wind, Wind::Gust(0, 2.5));
+        assert_eq!(round_trip(definition), "\
+{
+  weather: Rain {
+    intensity: 0.5,
+    thunder: false
+  },
+  wind: Gust {
+    0,
+    2.5
+  }
+}");
+
+        let definition= load::<Forecast>("{ weather: default, wind: Calm }");
+        assert_eq!(definition.get_definition().weather, Weather::Rain { intensity: 0.5, thunder: false });
+        assert_eq!(definition.get_definition().wind, Wind::Calm);
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    #[schema(discriminant)]
+    #[repr(u8)]
+    enum Priority {
+        Low,
+        #[schema(alias = "Normal")]
+        Medium= 5,
+        High,
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    #[schema(tag = "type")]
+    enum Action {
+        Wait,
+        Spawn { count: i32, #[schema(default)] delay: f32 },
+        #[schema(rename = "Say")]
+        Speak { #[schema(rename = "volume")] level: i32 },
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Task {
+        priority: Priority,
+        actions: SchemaArray<Action>,
+    }
+
+    #[test]
+    fn discriminants_and_tags() {
+        let definition= load::<Task>("{ priority: 6, actions: [{ type: \"Wait\" }, { count: 3, type: \"Spawn\" }, \
+            { type: \"Say\", volume: 2 }] }");
+        let task= definition.get_definition();
+        assert_eq!(task.priority, Priority::High);
+        let actions= task.actions.as_slice().unwrap();
+        assert_eq!(actions[..2], [Action::Wait, Action::Spawn { count: 3, delay: 0.0 }]);
+        assert_eq!(round_trip(definition), "\
+{
+  priority: 6,
+  actions: [
+    {
+      type: \"Wait\"
+    },
+    {
+      type: \"Spawn\",
+      count: 3,
+      delay: 0.0
+    },
+    {
+      type: \"Say\",
+      volume: 2
+    }
+  ]
+}");
+
+        // Variant names are read as well as numbers
+        assert_eq!(load::<Task>("{ priority: Normal, actions: [] }").get_definition().priority, Priority::Medium);
+
+        assert_eq!(load_errors::<Task>("{ priority: 3, actions: [{ kind: \"Wait\" }, { type: Wait }, \
+            { type: \"Jump\" }, { type: \"Spawn\", count: 1, size: 2 }, Wait] }"), [
+            "1:13: unknown identifier at 'priority' while deserializing Priority: expected a variant of Priority, found integer 3",
+            "1:26: missing field at 'actions[0].type' while deserializing Action",
+            "1:52: wrong value at 'actions[1].type' while deserializing Action: expected String, found enum variant Wait",
+            "1:68: unknown identifier at 'actions[2]' while deserializing Action: expected a variant of Action, \
+             found string \"Jump\"",
+            "1:105: unknown field at 'actions[3].size' while deserializing Action: expected one of: type, count, delay, \
+             found 'size'",
+            "1:116: wrong value at 'actions[4]' while deserializing Action: expected Object, found enum variant Wait",
+        ]);
+        assert_eq!(load_errors::<Task>("{ priority: 1.0, actions: [] }"),
+            ["1:13: wrong value at 'priority' while deserializing Priority: expected EnumVariant or Integer, found decimal 1.0"]);
+    }
+
+    #[derive(Schematize, Debug)]
+    enum Effect {
+        Heal(i32),
+        #[schema(other)]
+        Unknown(UnknownVariant),
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    enum Terrain {
+        Grass,
+        #[schema(other)]
+        Unknown,
+    }
+
+    #[derive(Schematize, Debug, PartialEq)]
+    #[schema(tag = "kind")]
+    enum Trigger {
+        Enter { radius: i32 },
+        #[schema(other)]
+        Unknown,
+    }
+
+    #[derive(Schematize, Debug)]
+    struct Tile {
+        effects: SchemaArray<Effect>,
+        terrain: Terrain,
+        trigger: Trigger,
+    }
+
+    #[test]
+    fn other_variants() {
+        // Variants from a newer schema are kept by an UnknownVariant, and written back out unchanged
+        let definition= load::<Tile>("{ effects: [Heal { 2 }, Burn { damage: 3, ticks: [1, 2] }, Freeze], \
+            terrain: Grass, trigger: { kind: \"Enter\", radius: 4 } }");
+        let effects= definition.get_definition().effects.as_slice().unwrap();
+        assert!(matches!(effects[0], Effect::Heal(2)));
+        match &effects[1] {
+            Effect::Unknown(variant) => assert_eq!((variant.name(), variant.fields()), ("Burn", Some("damage: 3,\nticks: [\n  1,\n  2\n]"))),
+            effect => panic!("expected an unknown variant, found {:?}", effect),
+        }
+        match &effects[2] {
+            Effect::Unknown(variant) => assert_eq!((variant.name(), variant.fields()), ("Freeze", None)),
+            effect => panic!("expected an unknown variant, found {:?}", effect),
+        }
+        assert_eq!(round_trip(definition), "\
+{
+  effects: [
+    Heal {
+      2
+    },
+    Burn {
+      damage: 3,
+      ticks: [
+        1,
+        2
+      ]
+    },
+    Freeze
+  ],
+  terrain: Grass,
+  trigger: {
+    kind: \"Enter\",
+    radius: 4
+  }
+}");
+
+        // A unit variant loses them, with a warning
+        let definition= load::<Tile>("{ effects: [], terrain: Lava, trigger: { kind: \"Exit\", radius: 4 } }");
+        assert_eq!(definition.get_definition().terrain, Terrain::Unknown);
+        assert_eq!(definition.get_definition().trigger, Trigger::Unknown);
+        assert_eq!(definition.get_warnings().iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "1:25: unknown identifier at 'terrain' while deserializing Terrain: expected a variant of Terrain, \
+             found enum variant Lava",
+            "1:48: unknown identifier at 'trigger' while deserializing Trigger: expected a variant of Trigger, \
+             found string \"Exit\"",
+        ]);
+        assert_eq!(serialize_definition(definition), "{\n  effects: [],\n  terrain: Unknown,\n  trigger: {\n    kind: \"Unknown\"\n  }\n}");
+    }
+}
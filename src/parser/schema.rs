@@ -1,173 +1,383 @@
-use crate::SchemaValue;
-use crate::parser::tokens::{Token, Symbol};
-use super::ParseResult;
-use crate::parser::debug;
+use crate::{SchemaObject, SchemaValue, SchemaValueKind};
+use crate::parser::tokens::{Token, TokenKind, Symbol};
+use super::{Grammar, ParseError, ParseResult, Span};
 
-use std::collections;
 
 macro_rules! consume_next_token {
-    ($array: ident, $index: ident,  $expected_token: expr) => {
-        if *$index >= $array.len() {
-            return Err("Reached EOF while parsing object, expected another token.");
+    ($parser: ident, $expected_token: expr) => {
+        let token= $parser.peek();
+        if token.kind != $expected_token {
+            return Err(unexpected_token(token, $expected_token.to_string()));
         }
+        $parser.index+= 1;
+    }
+}
+
+fn unexpected_token(token: &Token, expected: impl Into<String>) -> ParseError {
+    ParseError::new(token.span, expected, token.kind.to_string())
+}
+
+// A parsed definition file, and the version of the schema it was written with
+pub struct ParsedDefinition<'a> {
+    pub version: u32,           // 0 if the file has no version header
+    pub version_span: Span,     // location of the version header, or of the definition if there is none
+    pub schema_value: SchemaValue<'a>,
+}
+
+// Parses the token stream into schema values. The parser doesn't stop at the first error: it records the
+// error, skips ahead to the next ',', '}' or ']' and carries on, so every error in the file is reported in one pass.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    index: usize,
+    grammar: Grammar,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &'a Token {
+        &self.tokens[self.index]
+    }
 
-        let token= &$array[*$index];
-        if *token != $expected_token {
-            println!("Consuming next token, expected: '{:?}', found: '{:?}'", $expected_token, token);
-            return Err("Invalid token encountered.")
+    // Returns the next token, without advancing past the end of file token.
+    fn next_token(&mut self) -> &'a Token {
+        let token= &self.tokens[self.index];
+        if token.kind != TokenKind::EndOfFile {
+            self.index+= 1;
+        }
+        token
+    }
+
+    // Records an error and keeps parsing. An error at the same location as the previous one is a knock-on
+    // effect of it (e.g. every unclosed object hitting the end of file), so it is dropped.
+    fn report(&mut self, error: ParseError) {
+        if self.errors.last().map(|last| last.span) != Some(error.span) {
+            self.errors.push(error);
+        }
+    }
+
+    // Skip ahead to the next ',' or unmatched '}' or ']' without consuming it. This is where the enclosing
+    // object or array picks up again after an error.
+    fn synchronize(&mut self) {
+        let mut depth= 0;
+        loop {
+            match &self.peek().kind {
+                TokenKind::EndOfFile => return,
+                TokenKind::Punctuation(Symbol::OpenCurlyBrace | Symbol::OpenBrace) => depth+= 1,
+                TokenKind::Punctuation(Symbol::CloseCurlyBrace | Symbol::CloseBrace) => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth-= 1;
+                },
+                TokenKind::Punctuation(Symbol::Comma) if depth == 0 => return,
+                _ => (),
+            }
+            self.index+= 1;
         }
-        *$index+= 1;
     }
-}
 
-fn parse_value<'a>(tokens: &'a Vec<Token>, index: &mut usize) -> ParseResult<SchemaValue<'a>> {
-    if *index < tokens.len() {
-        let token= &tokens[*index];
-        *index+= 1;
+    // Parse the optional version header at the start of the file, e.g. `version 2`
+    fn parse_version(&mut self) -> ParseResult<Option<(u32, Span)>> {
+        match &self.peek().kind {
+            TokenKind::Identifier(ident) if ident == "version" => {
+                self.index+= 1;
+                let token= self.next_token();
+                match token.kind {
+                    TokenKind::Integer(version) if (0..=u32::MAX as i64).contains(&version) =>
+                        Ok(Some((version as u32, token.span))),
+                    _ => Err(unexpected_token(token, "a version number")),
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<SchemaValue<'a>> {
+        let token= self.peek();
 
-        match token {
-            Token::Integer(num) => Ok(SchemaValue::Integer(*num)),
-            Token::Decimal(num) => Ok(SchemaValue::Decimal(*num)),
-            Token::String(str) => Ok(SchemaValue::String(str)),
-            Token::Identifier(ident) => {
+        // Leave separators and closing braces for the enclosing object or array, the value is most likely
+        // just missing, e.g. `x: ,`
+        if matches!(token.kind, TokenKind::EndOfFile
+                | TokenKind::Punctuation(Symbol::Comma | Symbol::CloseCurlyBrace | Symbol::CloseBrace)) {
+            return Err(unexpected_token(token, "a value"));
+        }
+        self.index+= 1;
+
+        let kind= match &token.kind {
+            TokenKind::Integer(num) => SchemaValueKind::Integer(*num),
+            TokenKind::Decimal(num) => SchemaValueKind::Decimal(*num),
+            TokenKind::String(str) => SchemaValueKind::String(str),
+            TokenKind::Identifier(ident) => {
                 if ident == "true" {
-                    Ok(SchemaValue::Bool(true))
+                    SchemaValueKind::Bool(true)
                 } else if ident == "false" {
-                    Ok(SchemaValue::Bool(false))
+                    SchemaValueKind::Bool(false)
+                } else if ident == "inf" {
+                    SchemaValueKind::Decimal(f64::INFINITY)
+                } else if ident == "nan" {
+                    SchemaValueKind::Decimal(f64::NAN)
+                } else if ident == "default" {
+                    SchemaValueKind::Default
                 } else {
                     // Assume this is an enum variant... we could do better here.
                     // Maybe checking explicitly if this is a valid enum (requires type info)
-                    parse_enum(&ident, tokens, index)
+                    self.parse_enum(ident, token.span)?
                 }
             }
-            Token::Punctuation(Symbol::OpenBrace) => {
-                parse_array(tokens, index)
+            TokenKind::RawIdentifier(ident) => {
+                self.parse_enum(ident, token.span)?
             }
-            Token::Punctuation(Symbol::OpenCurlyBrace) => {
-                parse_object(tokens, index)
+            TokenKind::Punctuation(Symbol::OpenBrace) => {
+                self.parse_array()?
             }
-            _ => {
-                debug::print_tokens(tokens, *index-1);
-                println!("Found invalid token '{:?}' while parsing value.", token);
-                Err("Invalid token found while parsing value.")
+            TokenKind::Punctuation(Symbol::OpenCurlyBrace) => {
+                self.parse_object()?
+            }
+            // The tokenizer has already reported this literal
+            TokenKind::Invalid => SchemaValueKind::Null,
+            _ => return Err(unexpected_token(token, "a value")),
+        };
+
+        Ok(SchemaValue { kind, span: token.span })
+    }
+
+    fn parse_array(&mut self) -> ParseResult<SchemaValueKind<'a>> {
+        let mut vector= Vec::new();
+
+        // Special case: check for an empty array `[]`
+        if self.peek().kind == TokenKind::Punctuation(Symbol::CloseBrace) {
+            // consume the close brace, and return an empty vector
+            self.index+= 1;
+            return Ok(SchemaValueKind::Array(vector));
+        }
+
+        loop {
+            // Read the value
+            match self.parse_value() {
+                Ok(schema_value) => vector.push(schema_value),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize();
+                }
+            }
+
+            // Read the separator, skipping over anything unexpected in front of it
+            loop {
+                let token= self.next_token();
+                match token.kind {
+                    TokenKind::Punctuation(Symbol::Comma) => {
+                        // The lenient grammar allows a trailing comma, e.g. [1, 2, 3,]
+                        if self.grammar == Grammar::Lenient && self.peek().kind == TokenKind::Punctuation(Symbol::CloseBrace) {
+                            self.index+= 1;
+                            return Ok(SchemaValueKind::Array(vector));
+                        }
+                        // Read the next value...
+                        break;
+                    },
+                    TokenKind::Punctuation(Symbol::CloseBrace) => return Ok(SchemaValueKind::Array(vector)),
+                    TokenKind::EndOfFile => return Err(unexpected_token(token, "',' or ']'")),
+                    _ => {
+                        self.report(unexpected_token(token, "',' or ']'"));
+                        self.synchronize();
+                    }
+                }
             }
         }
-    } else {
-        Err("Reached end of token stream while trying to parse value.")
     }
-}
 
-fn parse_array<'a>(tokens: &'a Vec<Token>, index: &mut usize) -> ParseResult<SchemaValue<'a>> {
-    let mut vector= Vec::new();
-
-    // Special case: check for an empty array `[]`
-    if *index < tokens.len() {
-        // peek the next character
-        match &tokens[*index] {
-            Token::Punctuation(symbol) => match symbol {
-                Symbol::CloseBrace => {
-                    // consume the close brace, and return an empty vector
-                    *index+= 1;
-                    return Ok(SchemaValue::Array(vector));
+    fn parse_object(&mut self) -> ParseResult<SchemaValueKind<'a>> {
+        let mut fields_map= SchemaObject::new();
+
+        // Parse an object of format: { field_name: <value>, ...,  }
+        // With the lenient grammar commas are completely optional. With the strict grammar fields must
+        // be separated by exactly one comma, and there can't be a trailing comma.
+        let mut expecting_field= true;
+        loop {
+            let token= self.next_token();
+            match &token.kind {
+                TokenKind::Identifier(ident) | TokenKind::RawIdentifier(ident) => {
+                    if self.grammar == Grammar::Strict && !expecting_field {
+                        self.report(unexpected_token(token, "',' or '}'"));
+                    }
+
+                    // Parsing a field of this struct
+                    if let Err(error)= self.parse_field(ident, token.span, &mut fields_map) {
+                        self.report(error);
+                        self.synchronize();
+                    }
+                    expecting_field= false;
+                }
+                TokenKind::Punctuation(Symbol::Comma) => {
+                    if self.grammar == Grammar::Strict && expecting_field {
+                        self.report(unexpected_token(token, "a field name or '}'"));
+                    }
+                    expecting_field= true;
                 },
-                _ => ()
-            },
-            _ => ()
+                TokenKind::Punctuation(Symbol::CloseCurlyBrace) => {
+                    if self.grammar == Grammar::Strict && expecting_field && !fields_map.is_empty() {
+                        self.report(unexpected_token(token, "a field name after ','"));
+                    }
+                    return Ok(SchemaValueKind::Object(fields_map))
+                }
+                TokenKind::EndOfFile => return Err(unexpected_token(token, "a field name or '}'")),
+                _ => {
+                    self.report(unexpected_token(token, "a field name or '}'"));
+                    self.synchronize();
+                }
+            }
         }
     }
 
-    while *index < tokens.len() {
-        // Read the value
-        let schema_value= parse_value(tokens, index)?;
-        vector.push(schema_value);
+    fn parse_field(&mut self, name: &'a str, name_span: Span, fields_map: &mut SchemaObject<'a>) -> ParseResult<()> {
+        // A duplicate is still parsed, so that any errors in its value are reported too
+        let previous_span= fields_map.get_field(name).map(|previous| previous.name_span);
+        if let Some(previous_span)= previous_span {
+            self.report(ParseError::new(name_span, "a unique field name", format!("duplicate field '{}'", name))
+                .with_related(previous_span));
+        }
 
-        let token= &tokens[*index];
-        *index+= 1;
-        match token {
-            Token::Punctuation(Symbol::Comma) => (), // Read the next value...
-            Token::Punctuation(Symbol::CloseBrace) => return Ok(SchemaValue::Array(vector)),
-            _ => {
-                debug::print_tokens(tokens, *index-1);
-                println!("Found invalid token '{:?}' while parsing array.", token);
-                return Err("Invalid token found while parsing array.")
-            }
+        consume_next_token!(self, TokenKind::Punctuation(Symbol::Colon));
+        let field_value= self.parse_value()?;
+
+        if previous_span.is_none() {
+            fields_map.insert(name, name_span, field_value);
         }
+        Ok(())
     }
 
-    Err("Reached EOF while parsing array.")
-}
+    // Is the next token the name of a field, e.g. `count:`
+    fn at_field_name(&self) -> bool {
+        matches!(self.peek().kind, TokenKind::Identifier(_) | TokenKind::RawIdentifier(_))
+            && self.tokens.get(self.index + 1).map(|token| &token.kind) == Some(&TokenKind::Punctuation(Symbol::Colon))
+    }
 
-fn parse_object<'a>(tokens: &'a Vec<Token>, index: &mut usize) -> ParseResult<SchemaValue<'a>> {
-    let mut fields_map= collections::HashMap::<&str, SchemaValue>::new();
-
-    // Parse an object of format: { field_name: <value>, ...,  }
-    // NOTE: due to current implementation, commas are completely optional
-    while *index < tokens.len() {
-        let token= &tokens[*index];
-        *index+= 1;
-        match token {
-            Token::Identifier(ident) => {
-                // Parsing a field of this struct
-                consume_next_token!(tokens, index, Token::Punctuation(Symbol::Colon));
-                let field_value= parse_value(tokens, index)?;
-                fields_map.insert(&ident, field_value);
-            }
-            Token::Punctuation(Symbol::Comma) => (),
-            Token::Punctuation(Symbol::CloseCurlyBrace) => {
-                return Ok(SchemaValue::Object(fields_map))
+    fn parse_enum(&mut self, ident: &'a str, span: Span) -> ParseResult<SchemaValueKind<'a>> {
+        // peek the next token
+        if self.peek().kind != TokenKind::Punctuation(Symbol::OpenCurlyBrace) {
+            // TODO: Don't allocate any more if Null.
+            let variant_field= SchemaValue { kind: SchemaValueKind::Null, span };
+            return Ok(SchemaValueKind::EnumVariant(ident, Box::new(variant_field)));
+        }
+
+        // consume the open brace
+        let open_span= self.next_token().span;
+
+        // A struct-like variant holds fields, e.g. `Spawn { count: 3, delay: 0.5 }`
+        if self.at_field_name() {
+            let fields= self.parse_object()?;
+            let variant_field= SchemaValue { kind: fields, span: open_span };
+            return Ok(SchemaValueKind::EnumVariant(ident, Box::new(variant_field)));
+        }
+
+        // Otherwise it holds a value for each of its fields, e.g. `Damage { 2.5, Fire }`
+        let mut values= Vec::new();
+        loop {
+            match self.parse_value() {
+                Ok(field_value) => values.push(field_value),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize();
+                }
             }
-            _ => {
-                debug::print_tokens(tokens, *index-1);
-                println!("Found invalid token '{:?}' while parsing object.", token);
-                return Err("Invalid token found while parsing object.")
+
+            let token= self.peek();
+            match token.kind {
+                TokenKind::Punctuation(Symbol::Comma) => {
+                    self.index+= 1;
+                    // The lenient grammar allows a trailing comma, e.g. Damage { 2.5, Fire, }
+                    if self.grammar == Grammar::Lenient && self.peek().kind == TokenKind::Punctuation(Symbol::CloseCurlyBrace) {
+                        self.index+= 1;
+                        break;
+                    }
+                },
+                _ => {
+                    consume_next_token!(self, TokenKind::Punctuation(Symbol::CloseCurlyBrace));
+                    break;
+                }
             }
         }
-    }
 
-    Err("Reached EOF while parsing object.")
+        // A variant with a single field holds its value as is, several fields are held as an array
+        // TODO: Shouldn't allocate this on the heap.
+        let variant_field= match values.len() {
+            1 => values.pop().unwrap(),
+            _ => SchemaValue { kind: SchemaValueKind::Array(values), span: open_span },
+        };
+        Ok(SchemaValueKind::EnumVariant(ident, Box::new(variant_field)))
+    }
 }
 
-fn parse_enum<'a>(ident: &'a String, tokens: &'a Vec<Token>, index: &mut usize) -> ParseResult<SchemaValue<'a>> {
-    let variant_field= if *index < tokens.len() {
-        // peek the next character
-        match &tokens[*index] {
-            Token::Punctuation(symbol) => match symbol {
-                Symbol::OpenCurlyBrace => {
-                    // consume the open brace
-                    *index+= 1;
-
-                    let field_value= parse_value(tokens, index)?;
+// Returns the parsed definition, or every error found while parsing it.
+pub fn tokens_to_schema_value(tokens: &[Token], grammar: Grammar) -> Result<ParsedDefinition<'_>, Vec<ParseError>> {
+    let mut parser= Parser {
+        tokens,
+        index: 0,
+        grammar,
+        errors: Vec::new(),
+    };
 
-                    consume_next_token!(tokens, index, Token::Punctuation(Symbol::CloseCurlyBrace));
+    let version= match parser.parse_version() {
+        Ok(version) => version,
+        Err(error) => {
+            parser.report(error);
+            None
+        }
+    };
 
-                    field_value
-                },
-                _ => SchemaValue::Null
-            },
-            _ => SchemaValue::Null
+    let token= parser.next_token();
+    let result= match token.kind {
+        TokenKind::Punctuation(Symbol::OpenCurlyBrace) => {
+            parser.parse_object().map(|kind| SchemaValue { kind, span: token.span })
         }
-    } else {
-        SchemaValue::Null
+        _ => Err(unexpected_token(token, "'{' at the start of the definition")),
     };
 
-    // TODO: Shouldn't allocate this on the heap.
-    // TODO: Don't allocate any more if Null.
-    Ok(SchemaValue::EnumVariant(ident, Box::new(variant_field)))
-}
+    // Nothing may follow the root object, e.g. the rest of the fields after a stray '}' closed it early
+    let result= result.and_then(|schema_value| match parser.peek().kind {
+        TokenKind::EndOfFile => Ok(schema_value),
+        _ => Err(unexpected_token(parser.peek(), "end of file")),
+    });
 
-pub fn tokens_to_schema_value(tokens: &Vec<Token>) -> ParseResult<SchemaValue> {
-    if tokens.len() == 0 {
-        return Err("Cannot parse empty token stream.");
+    match result {
+        Ok(schema_value) if parser.errors.is_empty() => {
+            let (version, version_span)= version.unwrap_or((0, schema_value.span));
+            Ok(ParsedDefinition { version, version_span, schema_value })
+        },
+        Ok(_) => Err(parser.errors),
+        Err(error) => {
+            parser.report(error);
+            Err(parser.errors)
+        }
     }
+}
 
-    let mut index= 0;
-    match tokens[index] {
-        Token::Punctuation(Symbol::OpenCurlyBrace) => {
-            index+= 1;
-            parse_object(tokens, &mut index)
-        }
-        _ => {
-            return Err("Token stream does not represent valid object, should start with '{'.")
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokens::string_to_tokens;
+
+    // The errors for parsing `contents`, formatted as "line:column: expected ..., found ..."
+    fn parse_errors(contents: &str, grammar: Grammar) -> Vec<String> {
+        let (tokens, errors)= string_to_tokens(contents);
+        assert!(errors.is_empty(), "{:?}", errors);
+        match tokens_to_schema_value(&tokens, grammar) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
         }
     }
+
+    #[test]
+    fn trailing_tokens() {
+        assert_eq!(parse_errors("{ a: 1, b: 2.0 }", Grammar::Lenient), Vec::<String>::new());
+        assert_eq!(parse_errors("{ a: 1, b: 2.0 } b: 5.0 }", Grammar::Lenient),
+            ["1:18: expected end of file, found b"]);
+        assert_eq!(parse_errors("{ a: 1, b: 2.0 }\n{ a: 9, b: 9.0 }", Grammar::Lenient),
+            ["2:1: expected end of file, found '{'"]);
+    }
+    #[test]
+    fn strict_trailing_tokens() {
+        assert_eq!(parse_errors("{ a: 1, b: 2.0 }", Grammar::Strict), Vec::<String>::new());
+        assert_eq!(parse_errors("{ a: 1, b: 2.0 } garbage [", Grammar::Strict),
+            ["1:18: expected end of file, found garbage"]);
+    }
 }
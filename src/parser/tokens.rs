@@ -1,6 +1,7 @@
 use std::iter;
+use std::num;
 use std::str;
-use super::ParseResult;
+use super::{ParseError, ParseResult, Span};
 
 #[derive(PartialEq)]
 pub enum Symbol {
@@ -27,113 +28,554 @@ impl Symbol {
 }
 
 #[derive(PartialEq, Debug)]
-pub enum Token {
+pub enum TokenKind {
     Identifier(String), // A literal identifier (e.g. field name or an enum variant)
+    RawIdentifier(String), // An identifier written as r#ident, stored without the r# prefix
     String(String),
     Integer(i64),
     Decimal(f64),
     Punctuation(Symbol),
+    Invalid,            // A literal that failed to tokenize. Its error has already been reported.
+    EndOfFile,          // Always the last token in the stream, so the parser never reads out of bounds
 }
 
+#[derive(PartialEq, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,     // Location of the first character of this token in the source
+}
+
+// Iterates over the characters of the source, keeping track of the current line and column.
+struct Cursor<'a> {
+    chars: iter::Peekable<str::CharIndices<'a>>,
+    source_len: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(contents: &'a str) -> Cursor<'a> {
+        Cursor {
+            chars: contents.char_indices().peekable(),
+            source_len: contents.len(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, ch)| *ch)
+    }
+
+    // Look two characters ahead, e.g. to tell a comment apart from a stray '/'
+    fn peek_second(&self) -> Option<char> {
+        self.chars.clone().nth(1).map(|(_, ch)| ch)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (_, ch)= self.chars.next()?;
+        if ch == '\n' {
+            self.line+= 1;
+            self.column= 1;
+        } else {
+            self.column+= 1;
+        }
+        Some(ch)
+    }
+
+    // The location of the next character to be read
+    fn span(&mut self) -> Span {
+        let offset= match self.chars.peek() {
+            Some((offset, _)) => *offset,
+            None => self.source_len,
+        };
+
+        Span {
+            offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+fn describe_char(ch: char) -> String {
+    format!("'{}'", ch.escape_debug())
+}
+
+// Parse a numeric literal, for example:
+//   42, -7, 1_000_000, 0xFF, 0o17, 0b1010    (integers, must fit in an i64)
+//   1.5, .5, -2., 1e-3, 6.02E23, inf, -inf, nan    (decimals, must fit in an f64)
+fn parse_number(cursor: &mut Cursor) -> ParseResult<TokenKind> {
+    let start= cursor.span();
+    let mut literal= String::new(); // the literal as written, used for error messages
+    let mut digits= String::new();  // the literal without digit separators, used for parsing
+
+    if let Some(sign @ ('-' | '+'))= cursor.peek() {
+        cursor.next();
+        literal.push(sign);
+        digits.push(sign);
+    }
+
+    // Special decimal values, e.g. `-inf`
+    if cursor.peek().is_some_and(|ch| ch.is_alphabetic()) {
+        while let Some(next_char)= cursor.peek().filter(|ch| ch.is_alphanumeric()) {
+            literal.push(next_char);
+            cursor.next();
+        }
+
+        return match literal.trim_start_matches(['-', '+']) {
+            "inf" if literal.starts_with('-') => Ok(TokenKind::Decimal(f64::NEG_INFINITY)),
+            "inf" => Ok(TokenKind::Decimal(f64::INFINITY)),
+            "nan" => Ok(TokenKind::Decimal(f64::NAN)),
+            _ => Err(ParseError::new(start, "a number", format!("'{}'", literal))),
+        };
+    }
+
+    // Integers with a radix prefix, e.g. 0xFF
+    let radix= match (cursor.peek(), cursor.peek_second()) {
+        (Some('0'), Some('x' | 'X')) => 16,
+        (Some('0'), Some('o' | 'O')) => 8,
+        (Some('0'), Some('b' | 'B')) => 2,
+        _ => 10,
+    };
+
+    if radix != 10 {
+        // consume the prefix
+        literal.push(cursor.next().unwrap());
+        literal.push(cursor.next().unwrap());
+
+        while let Some(next_char)= cursor.peek().filter(|ch| ch.is_alphanumeric() || *ch == '_') {
+            literal.push(next_char);
+            if next_char != '_' {
+                digits.push(next_char);
+            }
+            cursor.next();
+        }
+
+        return match i64::from_str_radix(&digits, radix) {
+            Ok(number) => Ok(TokenKind::Integer(number)),
+            Err(err) => match err.kind() {
+                num::IntErrorKind::PosOverflow | num::IntErrorKind::NegOverflow =>
+                    Err(ParseError::new(start, "an integer in the range of i64", format!("'{}' which overflows i64", literal))),
+                _ =>
+                    Err(ParseError::new(start, format!("an integer of base {}", radix), format!("'{}'", literal))),
+            }
+        };
+    }
 
-fn parse_number(chars: &mut iter::Peekable<str::Chars>) -> ParseResult<Token> {
-    let mut accumulator= String::new();
     let mut is_decimal= false;
+    let mut has_exponent= false;
+    let mut second_period= None; // reported once the whole literal has been consumed
 
-    while let Some(next_char)= chars.peek() {
+    while let Some(next_char)= cursor.peek() {
         match next_char {
-            '0'..='9' => accumulator.push(chars.next().unwrap()),
+            '0'..='9' => digits.push(next_char),
+            '_' => (), // digit separator
             '.' => {
-                accumulator.push(chars.next().unwrap());
-                if is_decimal {
-                    return Err("Hit a second period while parsing decimal number.");
+                if is_decimal && second_period.is_none() {
+                    second_period= Some(cursor.span());
                 }
+                digits.push(next_char);
+                is_decimal= true;
+            }
+            'e' | 'E' if !has_exponent => {
+                digits.push(next_char);
+                literal.push(next_char);
+                cursor.next();
                 is_decimal= true;
+                has_exponent= true;
+
+                // The exponent may be signed, e.g. 1e-3
+                if let Some(sign @ ('-' | '+'))= cursor.peek() {
+                    digits.push(sign);
+                    literal.push(sign);
+                    cursor.next();
+                }
+                continue;
             }
             _ => {
                 // Hit a non number character. Stop parsing this number
                 break;
             }
         }
+        literal.push(next_char);
+        cursor.next();
+    }
+
+    if let Some(span)= second_period {
+        return Err(ParseError::new(span, "a digit", "a second '.' in decimal number"));
     }
 
     if is_decimal {
-        let number= accumulator.parse::<f64>();
-        match number {
-            Ok(number) => return Ok(Token::Decimal(number)),
-            Err(_) => return Err("Failed to parse decimal number")
+        match digits.parse::<f64>() {
+            Ok(number) if number.is_infinite() =>
+                Err(ParseError::new(start, "a decimal in the range of f64", format!("'{}' which overflows f64", literal))),
+            Ok(number) => Ok(TokenKind::Decimal(number)),
+            Err(_) => Err(ParseError::new(start, "a decimal number", format!("'{}'", literal))),
         }
     } else {
-        let number= accumulator.parse::<i64>();
-        match number {
-            Ok(number) => return Ok(Token::Integer(number)),
-            Err(_) => return Err("Failed to parse integer")
+        match digits.parse::<i64>() {
+            Ok(number) => Ok(TokenKind::Integer(number)),
+            Err(err) => match err.kind() {
+                num::IntErrorKind::PosOverflow | num::IntErrorKind::NegOverflow =>
+                    Err(ParseError::new(start, "an integer in the range of i64", format!("'{}' which overflows i64", literal))),
+                _ =>
+                    Err(ParseError::new(start, "a number", format!("'{}'", literal))),
+            }
         }
     }
 }
 
-fn parse_identifier(chars: &mut iter::Peekable<str::Chars>) -> ParseResult<Token> {
+// Identifiers follow the same grammar as Rust, so any field or variant name accepted by
+// #[derive(Schematize)] can be written in a definition file.
+fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
+}
+
+// Returns true if the cursor is at the start of a raw identifier, e.g. r#type
+fn is_raw_identifier_start(cursor: &Cursor) -> bool {
+    let mut chars= cursor.chars.clone().map(|(_, ch)| ch);
+    chars.next() == Some('r') && chars.next() == Some('#') && chars.next().is_some_and(is_identifier_start)
+}
+
+fn parse_identifier(cursor: &mut Cursor) -> ParseResult<TokenKind> {
+    let mut accumulator= String::new();
+
+    while let Some(next_char)= cursor.peek() {
+        if !is_identifier_continue(next_char) {
+            // Hit a character that can't be part of an identifier, stop parsing this identifier
+            break;
+        }
+        accumulator.push(next_char);
+        cursor.next();
+    }
+
+    Ok(TokenKind::Identifier(accumulator))
+}
+
+// Raw identifiers are never treated as keywords, so r#true is the enum variant `true` rather than a bool.
+fn parse_raw_identifier(cursor: &mut Cursor) -> ParseResult<TokenKind> {
+    // consume the r#
+    cursor.next();
+    cursor.next();
+
+    match parse_identifier(cursor)? {
+        TokenKind::Identifier(ident) => Ok(TokenKind::RawIdentifier(ident)),
+        kind => Ok(kind),
+    }
+}
+
+// Parse the character(s) following a backslash in a string literal
+fn parse_escape(cursor: &mut Cursor, accumulator: &mut String) -> ParseResult<()> {
+    let start= cursor.span();
+    cursor.next(); // consume the backslash
+
+    let escaped= match cursor.next() {
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('\\') => '\\',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('0') => '\0',
+        Some(ch @ ('\n' | '\r')) if ch == '\n' || cursor.peek() == Some('\n') => {
+            // Line continuation: skip the newline (LF or CRLF) and any leading whitespace on the next line
+            while cursor.peek().is_some_and(|ch| ch.is_whitespace()) {
+                cursor.next();
+            }
+            return Ok(());
+        },
+        Some('u') => {
+            // Unicode escape, e.g. \u{1F600}
+            if cursor.next() != Some('{') {
+                return Err(ParseError::new(start, "'{' after \\u in unicode escape", "'\\u'"));
+            }
+
+            let mut hex= String::new();
+            loop {
+                match cursor.next() {
+                    Some('}') => break,
+                    Some(ch) if ch.is_ascii_hexdigit() && hex.len() < 6 => hex.push(ch),
+                    Some('_') => (),
+                    _ => return Err(ParseError::new(start, "1 to 6 hex digits followed by '}' in unicode escape",
+                                                    format!("'\\u{{{}'", hex))),
+                }
+            }
+
+            let code_point= u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+            match code_point {
+                Some(ch) => ch,
+                None => return Err(ParseError::new(start, "a unicode scalar value", format!("'\\u{{{}}}'", hex))),
+            }
+        },
+        Some(ch) => return Err(ParseError::new(start, "a valid escape sequence", format!("'\\{}'", ch.escape_debug()))),
+        None => return Err(ParseError::new(cursor.span(), "an escape sequence", "end of file")),
+    };
+
+    accumulator.push(escaped);
+    Ok(())
+}
+
+fn parse_string(cursor: &mut Cursor) -> ParseResult<TokenKind> {
+    let start= cursor.span();
+    cursor.next(); // consume the quotation mark that initiated this parse
     let mut accumulator= String::new();
+    let mut escape_error= None; // reported at the closing quotation mark, so the rest of the string is skipped
 
-    while let Some(next_char)= chars.peek() {
+    // Strings may span multiple lines, newlines are kept as is.
+    while let Some(next_char)= cursor.peek() {
         match next_char {
-            'a'..='z' | 'A'..='Z' | '_' => accumulator.push(chars.next().unwrap()),
+            '"' => {
+                cursor.next();
+                return match escape_error {
+                    Some(error) => Err(error),
+                    None => Ok(TokenKind::String(accumulator)),
+                };
+            },
+            '\\' => {
+                if let Err(error)= parse_escape(cursor, &mut accumulator) {
+                    escape_error= escape_error.or(Some(error));
+                }
+            },
             _ => {
-                // Hit a non alphabetic character, stop parsing this identifier
-                break;
-            }
+                accumulator.push(next_char);
+                cursor.next();
+            },
         }
     }
 
-    Ok(Token::Identifier(accumulator))
+    Err(ParseError::new(start, "closing quotation mark for string", "end of file"))
+}
+
+// Returns true if the cursor is at the start of a raw string, e.g. r"..." or r#"..."#
+fn is_raw_string_start(cursor: &Cursor) -> bool {
+    let mut chars= cursor.chars.clone().map(|(_, ch)| ch);
+    if chars.next() != Some('r') {
+        return false;
+    }
+    chars.find(|ch| *ch != '#') == Some('"')
 }
 
-fn parse_string(chars: &mut iter::Peekable<str::Chars>) -> ParseResult<Token> {
-    chars.next(); // consume the quotation mark that initiated this parse
+// Parse a raw string. Raw strings have no escape sequences, and are closed by a quotation mark followed
+// by as many '#' as were used to open it, so r#"say "hi""# is the text: say "hi"
+fn parse_raw_string(cursor: &mut Cursor) -> ParseResult<TokenKind> {
+    let start= cursor.span();
+    cursor.next(); // consume the 'r'
+
+    let mut hashes= 0;
+    while cursor.peek() == Some('#') {
+        cursor.next();
+        hashes+= 1;
+    }
+    cursor.next(); // consume the opening quotation mark
+
     let mut accumulator= String::new();
+    while let Some(next_char)= cursor.next() {
+        if next_char == '"' {
+            let closing_hashes= cursor.chars.clone()
+                .take(hashes)
+                .take_while(|(_, ch)| *ch == '#')
+                .count();
 
-    while let Some(next_char)= chars.next() {
-        match next_char {
-            '"' => return Ok(Token::String(accumulator)),
-            _ => accumulator.push(next_char),
+            if closing_hashes == hashes {
+                for _ in 0..hashes {
+                    cursor.next();
+                }
+                return Ok(TokenKind::String(accumulator));
+            }
+        }
+        accumulator.push(next_char);
+    }
+
+    Err(ParseError::new(start, format!("closing '\"{}' for raw string", "#".repeat(hashes)), "end of file"))
+}
+
+// Skip a `// ...` comment, up to (but not including) the end of the line
+fn skip_line_comment(cursor: &mut Cursor) {
+    while let Some(next_char)= cursor.peek() {
+        if next_char == '\n' {
+            break;
         }
+        cursor.next();
     }
+}
+
+// Skip a `/* ... */` comment. Block comments nest, so `/* a /* b */ c */` is a single comment.
+fn skip_block_comment(cursor: &mut Cursor) -> ParseResult<()> {
+    let start= cursor.span();
+    let mut depth= 0;
+
+    while let Some(next_char)= cursor.peek() {
+        match (next_char, cursor.peek_second()) {
+            ('/', Some('*')) => {
+                cursor.next();
+                depth+= 1;
+            },
+            ('*', Some('/')) => {
+                cursor.next();
+                depth-= 1;
+            },
+            _ => (),
+        }
+        cursor.next();
 
-    Err("Hit end of file while reading string, expected closing quotation mark.")
+        if depth == 0 {
+            return Ok(());
+        }
+    }
+
+    Err(ParseError::new(start, "'*/' to close block comment", "end of file"))
 }
 
-pub fn string_to_tokens(contents: &str) -> ParseResult<Vec<Token>> {
-    let mut chars= contents.chars().peekable();
+// Splits the source into tokens. Tokenizing doesn't stop at the first error: a literal that fails to
+// tokenize is replaced with an Invalid token and an unexpected character is skipped, so that the parser
+// can still report any errors that follow.
+pub fn string_to_tokens(contents: &str) -> (Vec<Token>, Vec<ParseError>) {
+    let mut cursor= Cursor::new(contents);
     let mut tokens= Vec::new();
+    let mut errors= Vec::new();
+
+    while let Some(next_char)= cursor.peek() {
+        let span= cursor.span();
 
-    while let Some(next_char)= chars.peek() {
         // Based on the next character, match the next token, ignoring any whitespace.
         let next_token= match next_char {
-            '0'..='9' => Some(parse_number(&mut chars)?),
-            'a'..='z' | 'A'..='Z' => Some(parse_identifier(&mut chars)?),
-            '"' => Some(parse_string(&mut chars)?),
+            '0'..='9' | '-' | '+' => Some(parse_number(&mut cursor)),
+            '.' if cursor.peek_second().is_some_and(|ch| ch.is_ascii_digit()) => Some(parse_number(&mut cursor)),
+            'r' if is_raw_string_start(&cursor) => Some(parse_raw_string(&mut cursor)),
+            'r' if is_raw_identifier_start(&cursor) => Some(parse_raw_identifier(&mut cursor)),
+            _ if is_identifier_start(next_char) => Some(parse_identifier(&mut cursor)),
+            '"' => Some(parse_string(&mut cursor)),
+            '/' if cursor.peek_second() == Some('/') => {
+                skip_line_comment(&mut cursor);
+                None
+            },
+            '/' if cursor.peek_second() == Some('*') => {
+                if let Err(error)= skip_block_comment(&mut cursor) {
+                    errors.push(error);
+                }
+                None
+            },
             _ => {
-                let token= if let Some(symbol)= Symbol::from_char(*next_char) {
-                    Some(Token::Punctuation(symbol))
+                let token= if let Some(symbol)= Symbol::from_char(next_char) {
+                    Some(Ok(TokenKind::Punctuation(symbol)))
                 } else if next_char.is_whitespace() {
                     // Ignore whitespace
                     None
                 } else {
-                    // Hit an unexpected symbol. Fail the parsing.
-                    println!("Found unexpected character {} while reading block definition", next_char);
-                    return Err("Hit unexpected character while parsing block definition");
+                    // Hit an unexpected symbol. Report it and skip over it.
+                    errors.push(ParseError::new(span, "a value, identifier or punctuation", describe_char(next_char)));
+                    None
                 };
 
-                chars.next();
+                cursor.next();
                 token
             }
         };
 
         match next_token {
-            Some(token) => tokens.push(token),
-            None => ()
+            Some(Ok(kind)) => tokens.push(Token { kind, span }),
+            Some(Err(error)) => {
+                errors.push(error);
+                tokens.push(Token { kind: TokenKind::Invalid, span });
+            },
+            None => (),
         }
     }
 
-    Ok(tokens)
+    tokens.push(Token { kind: TokenKind::EndOfFile, span: cursor.span() });
+
+    (tokens, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerializeContext;
+
+    // The tokens of `contents`, without the end of file token. Panics on any error.
+    fn tokens(contents: &str) -> Vec<TokenKind> {
+        let (tokens, errors)= string_to_tokens(contents);
+        assert!(errors.is_empty(), "{:?}", errors.iter().map(ToString::to_string).collect::<Vec<_>>());
+        let mut kinds= tokens.into_iter().map(|token| token.kind).collect::<Vec<_>>();
+        assert_eq!(kinds.pop(), Some(TokenKind::EndOfFile));
+        kinds
+    }
+
+    // The only error for tokenizing `contents`, formatted as "line:column: expected ..., found ..."
+    fn error(contents: &str) -> String {
+        let (_, errors)= string_to_tokens(contents);
+        assert_eq!(errors.len(), 1, "{:?}", errors.iter().map(ToString::to_string).collect::<Vec<_>>());
+        errors[0].to_string()
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(tokens("1 /* a /* nested */ comment */ 2"), [TokenKind::Integer(1), TokenKind::Integer(2)]);
+        assert_eq!(tokens("/**/ 1 // line comment\n 2"), [TokenKind::Integer(1), TokenKind::Integer(2)]);
+
+        assert_eq!(error("1 /* open"), "1:3: expected '*/' to close block comment, found end of file");
+        assert_eq!(error("/* a /* nested */"), "1:1: expected '*/' to close block comment, found end of file");
+    }
+    #[test]
+    fn integers() {
+        assert_eq!(tokens("42 -5 +7"), [TokenKind::Integer(42), TokenKind::Integer(-5), TokenKind::Integer(7)]);
+        assert_eq!(tokens("0xFF 0o17 0b1010"), [TokenKind::Integer(255), TokenKind::Integer(15), TokenKind::Integer(10)]);
+        assert_eq!(tokens("1_000_000 0xFF_FF"), [TokenKind::Integer(1_000_000), TokenKind::Integer(0xFFFF)]);
+        assert_eq!(tokens("9223372036854775807 -9223372036854775808"),
+            [TokenKind::Integer(i64::MAX), TokenKind::Integer(i64::MIN)]);
+    }
+
+    #[test]
+    fn decimals() {
+        assert_eq!(tokens("1.5 .5 -2. 1e-3 6.02E23"), [
+            TokenKind::Decimal(1.5),
+            TokenKind::Decimal(0.5),
+            TokenKind::Decimal(-2.0),
+            TokenKind::Decimal(1e-3),
+            TokenKind::Decimal(6.02e23),
+        ]);
+        assert_eq!(tokens("-inf +inf"), [TokenKind::Decimal(f64::NEG_INFINITY), TokenKind::Decimal(f64::INFINITY)]);
+
+        // A signed nan is a number, a bare `inf` or `nan` is a keyword for the parser
+        assert!(matches!(tokens("-nan")[..], [TokenKind::Decimal(number)] if number.is_nan()));
+        assert_eq!(tokens("nan inf"), [TokenKind::Identifier(String::from("nan")), TokenKind::Identifier(String::from("inf"))]);
+    }
+
+    #[test]
+    fn number_errors() {
+        assert_eq!(error("9223372036854775808"),
+            "1:1: expected an integer in the range of i64, found '9223372036854775808' which overflows i64");
+        assert_eq!(error("0x1_0000_0000_0000_0000"),
+            "1:1: expected an integer in the range of i64, found '0x1_0000_0000_0000_0000' which overflows i64");
+        assert_eq!(error("1e400"), "1:1: expected a decimal in the range of f64, found '1e400' which overflows f64");
+        assert_eq!(error("1.2.3"), "1:4: expected a digit, found a second '.' in decimal number");
+        assert_eq!(error("0xFG"), "1:1: expected an integer of base 16, found '0xFG'");
+        assert_eq!(error("-infinity"), "1:1: expected a number, found '-infinity'");
+    }
+    #[test]
+    fn escapes() {
+        assert_eq!(tokens(r#""a\"b\\c\n\t\0\u{1F600}""#), [TokenKind::String(String::from("a\"b\\c\n\t\0\u{1F600}"))]);
+        assert_eq!(tokens("\"line \\\n    continued\""), [TokenKind::String(String::from("line continued"))]);
+        assert_eq!(tokens("\"line \\\r\n    continued\""), [TokenKind::String(String::from("line continued"))]);
+        assert_eq!(tokens(r###"r#"say "hi""#"###), [TokenKind::String(String::from("say \"hi\""))]);
+
+        assert_eq!(error(r#""\q""#), "1:2: expected a valid escape sequence, found '\\q'");
+        assert_eq!(error(r#""\u{110000}""#), "1:2: expected a unicode scalar value, found '\\u{110000}'");
+        assert_eq!(error("r#\"open"), "1:1: expected closing '\"#' for raw string, found end of file");
+    }
+
+    // Strings written by the serializer read back as the same string
+    #[test]
+    fn escape_round_trip() {
+        for string in ["plain", "quote \" and \\ backslash", "lines\r\nand\ttabs", "nul \0 bell \u{7} del \u{7f}", "emoji \u{1F600}"] {
+            let mut context= SerializeContext::new();
+            crate::schema_string::serialize_str(string, &mut context);
+            assert_eq!(tokens(&context.string), [TokenKind::String(String::from(string))], "{}", context.string);
+        }
+    }
 }
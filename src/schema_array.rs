@@ -3,14 +3,15 @@ use super::block;
 
 use std::alloc;
 use std::fmt;
+use std::ptr;
 
 pub struct SchemaArray<T> {
     block_ptr: block::BlockPointer<T>,
     len: usize,
 }
 
-impl<'a, T> SchemaArray<T> {
-    pub fn as_slice(&self) -> Option<&'a [T]> {
+impl<T> SchemaArray<T> {
+    pub fn as_slice(&self) -> Option<&[T]> {
         if self.block_ptr.is_null() {
             None
         } else {
@@ -24,11 +25,15 @@ impl<'a, T> SchemaArray<T> {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     // TODO: This is very dangerous
     pub fn from_raw_parts(block_ptr: block::BlockPointer<T>, len: usize) -> SchemaArray<T> {
         SchemaArray {
-            block_ptr: block_ptr,
-            len: len,
+            block_ptr,
+            len,
         }
     }
 }
@@ -42,7 +47,9 @@ pub fn serialize_array<T: Schematize>(slice: &[T], context: &mut SerializeContex
     context.print_tabs();
 
     for (index, element) in slice.iter().enumerate() {
+        context.path.push(format!("[{}]", index));
         element.serialize(context);
+        context.path.pop();
         if index != slice.len()-1 {
             context.print(",\n");
             context.print_tabs();
@@ -75,13 +82,13 @@ impl<T: Schematize> Schematize for SchemaArray<T> {
 
     fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
         -> BuildLayoutResult {
-        match schema_value {
-            SchemaValue::Array(vector) => {
+        match &schema_value.kind {
+            SchemaValueKind::Array(vector) => {
                 // Need to build layout for elements...
                 // First we allocate the static sized block for the schema array pointers.
                 // If the elements are dynamically sized, well, more allocations...
 
-                if vector.len() > 0 {
+                if !vector.is_empty() {
                     // allocate entire static array block for the elements
                     let array_layout= alloc::Layout::array::<T>(vector.len())?;
                     let (mut new_layout, offset)= layout.extend(array_layout)?;
@@ -107,9 +114,9 @@ impl<T: Schematize> Schematize for SchemaArray<T> {
     }
 
     fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<SchemaArray<T>> {
-        match schema_value {
-            SchemaValue::Array(vector) => {
-                if vector.len() > 0 {
+        match &schema_value.kind {
+            SchemaValueKind::Array(vector) => {
+                if !vector.is_empty() {
                     // Get the block pointer offset for this array
                     assert!(context.offset_index < context.offsets.len());
                     let byte_offset= context.offsets[context.offset_index];
@@ -119,9 +126,15 @@ impl<T: Schematize> Schematize for SchemaArray<T> {
 
                     unsafe {
                         // Deserialize all the elements
-                        for index in 0..vector.len() {
+                        for (index, item) in vector.iter().enumerate() {
                             context.path.push(format!("[{}]", index));
-                            *block_pointer.get_pointer_mut().add(index)= T::deserialize(&vector[index], context)?;
+                            let offset_index= context.offset_index;
+                            let element= match deserialize_or_default(item, context) {
+                                Ok(element) => element,
+                                Err(error) => context.recover(error, item, offset_index),
+                            };
+                            // The block is uninitialized, so there's no previous element to drop
+                            block_pointer.get_pointer_mut().add(index).write(element);
                             context.path.pop();
                         }
                     }
@@ -136,10 +149,21 @@ impl<T: Schematize> Schematize for SchemaArray<T> {
                 }
             },
             _ => {
-                println!("Deserialize hit a wrong value for field '{}'. Expected: Array, found: {:?}",
-                    context.get_path(),
-                    schema_value);
-                return Err(SchemaError::WrongSchemaValue);
+                Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                    .with_type_name("SchemaArray")
+                    .with_expected("Array"))
+            }
+        }
+    }
+}
+
+// The elements are written into the block, which is freed without dropping anything in it. So the array
+// drops its elements, e.g. any heap memory owned by a skipped field.
+impl<T> Drop for SchemaArray<T> {
+    fn drop(&mut self) {
+        if !self.block_ptr.is_null() {
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.block_ptr.get_pointer_mut(), self.len));
             }
         }
     }
@@ -0,0 +1,271 @@
+use crate::*;
+use crate::parser::ParseError;
+
+use std::error;
+use std::fmt;
+use std::slice;
+use std::vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaErrorKind {
+    WrongSchemaValue,
+    MissingField,
+    WrongSizedArray,
+    NumberOutOfBounds,
+    UnknownField,
+    DuplicateField,     // a field written under more than one of its names, see #[schema(alias)]
+    Validation,         // a field failed a check such as #[schema(range(min = 0))]
+    UnknownIdentifier,
+    Version,            // the definition file was written with a schema version that can't be migrated
+    Syntax,             // the definition file could not be parsed
+    Layout,             // the memory layout for the definition could not be built
+    Io,                 // the definition file could not be read
+}
+
+impl fmt::Display for SchemaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description= match self {
+            SchemaErrorKind::WrongSchemaValue => "wrong value",
+            SchemaErrorKind::MissingField => "missing field",
+            SchemaErrorKind::WrongSizedArray => "wrong sized array",
+            SchemaErrorKind::NumberOutOfBounds => "number out of bounds",
+            SchemaErrorKind::UnknownField => "unknown field",
+            SchemaErrorKind::DuplicateField => "duplicate field",
+            SchemaErrorKind::Validation => "validation failed",
+            SchemaErrorKind::UnknownIdentifier => "unknown identifier",
+            SchemaErrorKind::Version => "unsupported version",
+            SchemaErrorKind::Syntax => "syntax error",
+            SchemaErrorKind::Layout => "failed to build layout",
+            SchemaErrorKind::Io => "failed to read file",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ErrorDetails {
+    kind: SchemaErrorKind,
+    path: String,                    // field path of the offending value, e.g. inner.point[2]
+    type_name: Option<&'static str>, // the Rust type that was being deserialized
+    expected: Option<String>,
+    found: Option<String>,
+    span: Option<Span>,              // location of the offending value in the definition file
+    related_span: Option<Span>,      // a second location involved in the error, e.g. a duplicate field
+    file: Option<String>,            // the definition file, filled in by parser::load_definition
+}
+
+// The details are boxed so that SchemaResult<T> stays small on the happy path.
+#[derive(Debug, Clone)]
+pub struct SchemaError(Box<ErrorDetails>);
+
+impl SchemaError {
+    pub fn new(kind: SchemaErrorKind) -> SchemaError {
+        SchemaError(Box::new(ErrorDetails {
+            kind,
+            path: String::new(),
+            type_name: None,
+            expected: None,
+            found: None,
+            span: None,
+            related_span: None,
+            file: None,
+        }))
+    }
+
+    pub fn with_path(mut self, path: String) -> SchemaError {
+        self.0.path= path;
+        self
+    }
+
+    pub fn with_type_name(mut self, type_name: &'static str) -> SchemaError {
+        self.0.type_name= Some(type_name);
+        self
+    }
+
+    pub fn with_expected(mut self, expected: impl Into<String>) -> SchemaError {
+        self.0.expected= Some(expected.into());
+        self
+    }
+
+    pub fn with_found(mut self, found: impl Into<String>) -> SchemaError {
+        self.0.found= Some(found.into());
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> SchemaError {
+        self.0.span= Some(span);
+        self
+    }
+
+    pub fn with_related_span(mut self, span: Span) -> SchemaError {
+        self.0.related_span= Some(span);
+        self
+    }
+
+    pub fn with_file(mut self, file: &str) -> SchemaError {
+        self.0.file= Some(String::from(file));
+        self
+    }
+
+    pub fn kind(&self) -> SchemaErrorKind {
+        self.0.kind
+    }
+
+    pub fn path(&self) -> &str {
+        &self.0.path
+    }
+
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.0.type_name
+    }
+
+    pub fn expected(&self) -> Option<&str> {
+        self.0.expected.as_deref()
+    }
+
+    pub fn found(&self) -> Option<&str> {
+        self.0.found.as_deref()
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.0.span
+    }
+
+    pub fn related_span(&self) -> Option<Span> {
+        self.0.related_span
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.0.file.as_deref()
+    }
+}
+
+impl From<ParseError> for SchemaError {
+    fn from(err: ParseError) -> SchemaError {
+        let error= SchemaError::new(SchemaErrorKind::Syntax)
+            .with_expected(err.expected)
+            .with_found(err.found)
+            .with_span(err.span);
+
+        match err.related {
+            Some(related) => error.with_related_span(related),
+            None => error,
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let details= &self.0;
+
+        // Location first, in the file:line:column format understood by most editors
+        if let Some(file)= &details.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(span)= &details.span {
+            write!(f, "{}:", span)?;
+        }
+        if details.file.is_some() || details.span.is_some() {
+            write!(f, " ")?;
+        }
+
+        write!(f, "{}", details.kind)?;
+        if !details.path.is_empty() {
+            write!(f, " at '{}'", details.path)?;
+        }
+        if let Some(type_name)= details.type_name {
+            write!(f, " while deserializing {}", type_name)?;
+        }
+
+        match (&details.expected, &details.found) {
+            (Some(expected), Some(found)) => write!(f, ": expected {}, found {}", expected, found)?,
+            (Some(expected), None) => write!(f, ": expected {}", expected)?,
+            (None, Some(found)) => write!(f, ": {}", found)?,
+            (None, None) => (),
+        }
+
+        if let Some(related)= details.related_span {
+            write!(f, " (see {})", related)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for SchemaError {}
+
+// Every error found while loading a definition file. The parser and the derived deserialize functions
+// keep going after an error, so a broken file can be fixed without reloading it once per mistake.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaErrors(Vec<SchemaError>);
+
+impl SchemaErrors {
+    pub fn new() -> SchemaErrors {
+        SchemaErrors::default()
+    }
+
+    pub fn push(&mut self, error: SchemaError) {
+        self.0.push(error);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, SchemaError> {
+        self.0.iter()
+    }
+
+    pub fn with_file(self, file: &str) -> SchemaErrors {
+        self.into_iter().map(|error| error.with_file(file)).collect()
+    }
+}
+
+impl From<SchemaError> for SchemaErrors {
+    fn from(error: SchemaError) -> SchemaErrors {
+        SchemaErrors(vec![error])
+    }
+}
+
+impl FromIterator<SchemaError> for SchemaErrors {
+    fn from_iter<I: IntoIterator<Item= SchemaError>>(iter: I) -> SchemaErrors {
+        SchemaErrors(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for SchemaErrors {
+    type Item= SchemaError;
+    type IntoIter= vec::IntoIter<SchemaError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SchemaErrors {
+    type Item= &'a SchemaError;
+    type IntoIter= slice::Iter<'a, SchemaError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+// One error per line
+impl fmt::Display for SchemaErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for SchemaErrors {}
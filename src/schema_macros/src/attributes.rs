@@ -0,0 +1,229 @@
+extern crate proc_macro2;
+
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+
+// What to do with fields in the definition file that the struct doesn't have
+#[derive(Default, PartialEq)]
+pub enum UnknownFields {
+    #[default]
+    Deny,       // fail to load, with an error for every unknown field
+    Warn,       // ignore them, with a warning for every unknown field
+    Collect,    // keep them, so they're written back out when the definition is serialized
+}
+
+// How an enum is written in the definition file
+#[derive(Default)]
+pub enum EnumRepr {
+    #[default]
+    External,           // the variant's name, followed by its fields if it has any, e.g. `Spawn { count: 3 }`
+    Discriminant,       // the discriminant of a fieldless enum, e.g. `2`, though its variant names are also read
+    Tagged(String),     // an object naming its variant in the given field, e.g. `{ type: "Spawn", count: 3 }`
+}
+
+// Inclusive bounds for a validation attribute, e.g. range(min = -5, max = 10). Either bound is optional,
+// and each is an expression which is compared against the field as is.
+#[derive(Default)]
+pub struct Bounds {
+    pub min: Option<proc_macro2::TokenStream>,
+    pub max: Option<proc_macro2::TokenStream>,
+}
+
+// Options set by #[schema(...)] markup on an item or a field, e.g. #[schema(default)]
+#[derive(Default)]
+pub struct SchemaAttributes {
+    // A missing field takes its value from schema_default(), instead of failing to deserialize.
+    // On a struct, this applies to every field, and on an enum to every field of its struct-like variants.
+    pub default: bool,
+    // Struct, or enum with struct-like variants, e.g. #[schema(unknown_fields = "warn")]
+    pub unknown_fields: UnknownFields,
+    // Enum only, e.g. #[schema(discriminant)] or #[schema(tag = "type")]
+    pub repr: EnumRepr,
+    // Struct or enum only. The version of the schema, e.g. #[schema(version = 2)]. Only the root type of a
+    // definition file is versioned, by the file's header, so a versioned type fails to load when it's nested
+    // in another definition. Its migrations would never run.
+    pub version: Option<u32>,
+    // Struct or enum only. Replaces the `T: Schematize` bound added for every type parameter, e.g.
+    // #[schema(bound = "T: Schematize + Copy")], or #[schema(bound = "")] for no bounds at all
+    pub bound: Option<Vec<syn::WherePredicate>>,
+    // Struct only. The struct is written exactly like its only field, e.g. a newtype such as Meters(f32)
+    pub transparent: bool,
+    // Struct or enum only. Runs after the item is deserialized, e.g. #[schema(post_load = check_bounds)]
+    // with a fn(&mut T) -> Result<(), SchemaErrors>
+    pub post_load: Option<syn::Path>,
+    // Field or variant only. The name written in the definition file, instead of the Rust name.
+    pub rename: Option<String>,
+    // Field or variant only. Other names accepted when reading a definition file, e.g. a previous name.
+    pub aliases: Vec<String>,
+    // Variant only. Variants with a name the enum doesn't know deserialize to this one, instead of failing,
+    // e.g. ones added by a newer version of the schema. It is either a unit variant, or has a single field
+    // which is deserialized from the whole variant, such as an UnknownVariant to write it back out. The
+    // variant of a tagged enum must be a unit variant, since an UnknownVariant can't keep a tagged object.
+    pub other: bool,
+    // Field only. The field is left out of the definition file, e.g. a runtime cache. On load it takes its
+    // value from the struct's schema_default(), where it is set to `skip_value` if given,
+    // e.g. #[schema(skip = "Cache::new()")], before any #[schema_default(...)] markup is applied.
+    pub skip: bool,
+    pub skip_value: Option<proc_macro2::TokenStream>,
+    // Field only. A module (or type) which schematizes the field in place of its type, for types that can't
+    // implement Schematize. It has the same functions as the trait, taking the field by reference, e.g.
+    // #[schema(with = duration_secs)] with duration_secs::serialize(value: &Duration, context), etc.
+    pub with: Option<syn::Path>,
+    // Field only. The fields of the field's struct are written inline in this struct's object, instead of
+    // in an object of their own, e.g. a common Transform. The field's type must be a struct with named fields,
+    // otherwise it doesn't implement SchemaFlatten and fails to compile.
+    pub flatten: bool,
+
+    // Field only. Checked after the field is deserialized, failures are reported as validation errors.
+    pub range: Option<Bounds>,          // e.g. range(min = 0.0, max = 1.0)
+    pub len: Option<Bounds>,            // e.g. len(max = 8), for SchemaArray, SchemaString and arrays
+    pub non_empty: bool,
+    pub validate: Vec<syn::Path>,       // e.g. validate = is_power_of_two, a fn(&T) -> Result<(), String>
+}
+
+// One item of #[schema(...)] markup: `name`, `name = value` or `name(items...)`
+struct SchemaMeta {
+    name: syn::Ident,
+    value: Option<proc_macro2::TokenStream>,   // the tokens after '=', up to the next ','
+    items: Option<Vec<SchemaMeta>>,            // the items inside the parentheses
+}
+
+impl syn::parse::Parse for SchemaMeta {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<SchemaMeta> {
+        let name= input.call(syn::Ident::parse_any)?;
+        let mut meta= SchemaMeta { name, value: None, items: None };
+
+        if input.peek(syn::Token![=]) {
+            input.parse::<syn::Token![=]>()?;
+            let mut value= proc_macro2::TokenStream::new();
+            while !input.is_empty() && !input.peek(syn::Token![,]) {
+                value.extend([input.parse::<proc_macro2::TokenTree>()?]);
+            }
+            if value.is_empty() {
+                return Err(input.error("expected a value after '='"));
+            }
+            meta.value= Some(value);
+        } else if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            let items= Punctuated::<SchemaMeta, syn::Token![,]>::parse_terminated(&content)?;
+            meta.items= Some(items.into_iter().collect());
+        }
+
+        Ok(meta)
+    }
+}
+
+impl SchemaMeta {
+    fn is_flag(&self) -> bool {
+        self.value.is_none() && self.items.is_none()
+    }
+
+    fn value(&self) -> &proc_macro2::TokenStream {
+        match &self.value {
+            Some(value) => value,
+            None => panic!("Expected a value for schema attribute {}, e.g. {} = ...", self.name, self.name),
+        }
+    }
+
+    fn str_value(&self) -> String {
+        match syn::parse2::<syn::LitStr>(self.value().clone()) {
+            Ok(str) => str.value(),
+            Err(_) => panic!("Expected a string for schema attribute {}, found {}", self.name, self.value()),
+        }
+    }
+
+    // Names are read back as identifiers, so they must follow the same grammar, e.g. #[schema(rename = "type")]
+    fn name_value(&self) -> String {
+        let name= self.str_value();
+        if syn::parse::Parser::parse_str(syn::Ident::parse_any, &name).is_err() {
+            panic!("Expected an identifier for schema attribute {}, found \"{}\"", self.name, name);
+        }
+        name
+    }
+
+    fn path_value(&self) -> syn::Path {
+        match syn::parse2::<syn::Path>(self.value().clone()) {
+            Ok(path) => path,
+            Err(_) => panic!("Expected a path for schema attribute {}, found {}", self.name, self.value()),
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        let items= match &self.items {
+            Some(items) if !items.is_empty() => items,
+            _ => panic!("Expected bounds for schema attribute {}, e.g. {}(min = 1, max = 10)", self.name, self.name),
+        };
+
+        let mut bounds= Bounds::default();
+        for item in items {
+            if item.name == "min" {
+                bounds.min= Some(item.value().clone());
+            } else if item.name == "max" {
+                bounds.max= Some(item.value().clone());
+            } else {
+                panic!("Unknown bound for schema attribute {}: {}, expected min or max", self.name, item.name);
+            }
+        }
+        bounds
+    }
+}
+
+pub fn parse_attributes(attrs: &[syn::Attribute]) -> SchemaAttributes {
+    let mut attributes= SchemaAttributes::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("schema")) {
+        let items= attr.parse_args_with(Punctuated::<SchemaMeta, syn::Token![,]>::parse_terminated)
+            .unwrap_or_else(|err| panic!("Unable to parse schema attribute {}: {}", quote!(#attr), err));
+
+        for meta in items {
+            match meta.name.to_string().as_str() {
+                "default" if meta.is_flag() => attributes.default= true,
+                "unknown_fields" => {
+                    attributes.unknown_fields= match meta.str_value().as_str() {
+                        "deny" => UnknownFields::Deny,
+                        "warn" => UnknownFields::Warn,
+                        "collect" => UnknownFields::Collect,
+                        value => panic!("Unknown value for schema attribute unknown_fields: \"{}\", \
+                                         expected \"deny\", \"warn\" or \"collect\"", value),
+                    };
+                },
+                "skip" if meta.is_flag() => attributes.skip= true,
+                "skip" => {
+                    let expr= meta.str_value().parse::<proc_macro2::TokenStream>()
+                        .expect("Unable to parse expression for schema attribute skip");
+                    attributes.skip= true;
+                    attributes.skip_value= Some(expr);
+                },
+                "version" => {
+                    let version= syn::parse2::<syn::LitInt>(meta.value().clone()).ok()
+                        .and_then(|int| int.base10_parse::<u32>().ok());
+                    attributes.version= Some(version.expect("Expected an unsigned integer for schema attribute version"));
+                },
+                "bound" => {
+                    let bound= syn::parse::Parser::parse_str(
+                        Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated, &meta.str_value());
+                    let bound= bound.unwrap_or_else(|err| panic!("Unable to parse schema attribute bound: {}", err));
+                    attributes.bound= Some(bound.into_iter().collect());
+                },
+                "transparent" if meta.is_flag() => attributes.transparent= true,
+                "discriminant" if meta.is_flag() => attributes.repr= EnumRepr::Discriminant,
+                "tag" => attributes.repr= EnumRepr::Tagged(meta.name_value()),
+                "post_load" => attributes.post_load= Some(meta.path_value()),
+                "with" => attributes.with= Some(meta.path_value()),
+                "flatten" if meta.is_flag() => attributes.flatten= true,
+                "rename" => attributes.rename= Some(meta.name_value()),
+                "alias" => attributes.aliases.push(meta.name_value()),
+                "other" if meta.is_flag() => attributes.other= true,
+                "range" => attributes.range= Some(meta.bounds()),
+                "len" => attributes.len= Some(meta.bounds()),
+                "non_empty" if meta.is_flag() => attributes.non_empty= true,
+                "validate" => attributes.validate.push(meta.path_value()),
+                _ => panic!("Unknown schema attribute: {}", meta.name),
+            }
+        }
+    }
+
+    attributes
+}
@@ -1,77 +1,272 @@
 extern crate proc_macro2;
 
-use quote::quote;
+use quote::{format_ident, quote};
+
+use crate::attributes;
+use crate::struct_derive;
 
 type EnumVariants= syn::punctuated::Punctuated<syn::Variant, syn::token::Comma>;
 
-pub fn derive_default_fn(
-    enum_ident: &syn::Ident,
-    enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+// The variables bound to the fields of a variant when matching on it
+fn field_bindings(fields: &syn::Fields) -> Vec<syn::Ident> {
+    (0..fields.len()).map(|index| format_ident!("field_{}", index)).collect()
+}
 
-    assert!(enum_variants.len() > 0, "Cannot schematize uninhabitable enum.");
+// Checks the markup of the enum and its variants, before any code is generated for them
+pub fn check_variants(enum_variants: &EnumVariants, enum_attributes: &attributes::SchemaAttributes) {
+    check_repr(enum_variants, enum_attributes);
+    other_variant(enum_variants);
+    for variant in enum_variants {
+        check_field_attributes(variant);
+    }
+}
 
-    // TODO: Look for schema_default markup
-    let default_variant= &enum_variants[0];
+// Matches a variant, binding its fields, e.g. `Self::Spawn { count: field_0, delay: field_1 }`
+fn variant_pattern(variant: &syn::Variant) -> proc_macro2::TokenStream {
+    let variant_ident= &variant.ident;
+    let field_bindings= field_bindings(&variant.fields);
+    match &variant.fields {
+        syn::Fields::Unit => quote! { Self::#variant_ident },
+        syn::Fields::Unnamed(_) => quote! { Self::#variant_ident(#(#field_bindings),*) },
+        syn::Fields::Named(fields) => {
+            let field_idents= fields.named.iter().map(|field| &field.ident);
+            quote! { Self::#variant_ident { #(#field_idents: #field_bindings),* } }
+        },
+    }
+}
 
-    let variant_construct= if default_variant.fields.len() > 0 {
-        let fields_default= default_variant.fields.iter().map(
-            |field| -> proc_macro2::TokenStream {
-                let field_type= &field.ty;
+// Tuple variants are written as a value for each field, so only named fields can have markup
+fn check_field_attributes(variant: &syn::Variant) {
+    for field in &variant.fields {
+        let field_attributes= attributes::parse_attributes(&field.attrs);
+        if field_attributes.skip || field_attributes.flatten {
+            panic!("Schema attributes skip and flatten are not supported on enum variant fields");
+        }
+        if field.ident.is_none() && field.attrs.iter().any(|attr| attr.path.is_ident("schema")) {
+            panic!("Schema attributes are only supported on the named fields of an enum variant");
+        }
+    }
+}
 
-                return quote! {
-                    #field_type::schema_default()
+// Only a fieldless enum has a discriminant to write, and a tagged variant's fields are read from the same
+// object as its tag, so they must be named. An unknown tagged variant can't be kept either.
+fn check_repr(enum_variants: &EnumVariants, enum_attributes: &attributes::SchemaAttributes) {
+    match &enum_attributes.repr {
+        attributes::EnumRepr::External => (),
+        attributes::EnumRepr::Discriminant => {
+            if let Some(variant)= enum_variants.iter().find(|variant| !matches!(variant.fields, syn::Fields::Unit)) {
+                panic!("Schema attribute discriminant requires an enum without fields, found fields on variant {}", variant.ident);
+            }
+        },
+        attributes::EnumRepr::Tagged(tag) => {
+            for variant in enum_variants {
+                if attributes::parse_attributes(&variant.attrs).other && !variant.fields.is_empty() {
+                    panic!("Schema attribute tag requires a unit #[schema(other)] variant, found a field on variant {}", variant.ident);
+                }
+                if let syn::Fields::Unnamed(_)= variant.fields {
+                    panic!("Schema attribute tag requires unit or struct-like variants, found unnamed fields on variant {}", variant.ident);
+                }
+                for field in &variant.fields {
+                    let field_names= crate::schema_input_names(field.ident.as_ref().unwrap(), &attributes::parse_attributes(&field.attrs));
+                    if field_names.contains(tag) {
+                        panic!("Variant {} has a field named {}, which is the tag of the enum", variant.ident, tag);
+                    }
                 }
             }
-        );
+        },
+    }
+}
 
-       quote! { #default_variant(#(#fields_default),*) }
-    } else {
-        quote! { #default_variant }
+// The #[schema(other)] variant, which unknown variant names deserialize to
+fn other_variant(enum_variants: &EnumVariants) -> Option<&syn::Variant> {
+    let mut other_variants= enum_variants.iter().filter(|variant| attributes::parse_attributes(&variant.attrs).other);
+    let other_variant= other_variants.next()?;
+    if let Some(variant)= other_variants.next() {
+        panic!("Only one variant can be marked #[schema(other)], found {} and {}", other_variant.ident, variant.ident);
+    }
+
+    match &other_variant.fields {
+        syn::Fields::Unit => (),
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (),
+        _ => panic!("The #[schema(other)] variant {} must be a unit variant or have a single unnamed field", other_variant.ident),
+    }
+    Some(other_variant)
+}
+
+// Whether a variant is read when its name is found. The field of a #[schema(other)] variant reads the whole
+// variant, so it's left to the fallback, even when its own name is used.
+fn matched_by_name(variant: &syn::Variant) -> bool {
+    !attributes::parse_attributes(&variant.attrs).other || variant.fields.is_empty()
+}
+
+// A value for a field of the default variant, e.g. `count = 3` in #[schema_default(count = 3)], or `0 = 2.5`
+// for a tuple variant
+struct PayloadDefault {
+    member: syn::Member,
+    value: proc_macro2::TokenStream,
+}
+
+impl syn::parse::Parse for PayloadDefault {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<PayloadDefault> {
+        let member= input.parse::<syn::Member>()?;
+        input.parse::<syn::Token![=]>()?;
+        let mut value= proc_macro2::TokenStream::new();
+        while !input.is_empty() && !input.peek(syn::Token![,]) {
+            value.extend([input.parse::<proc_macro2::TokenTree>()?]);
+        }
+        if value.is_empty() {
+            return Err(input.error("expected a value after '='"));
+        }
+        Ok(PayloadDefault { member, value })
+    }
+}
+
+// The variant marked with #[schema_default], or the first variant if there is none. The #[schema(other)]
+// variant stands for a variant that isn't known, so it's only the default when marked.
+fn default_variant(enum_variants: &EnumVariants) -> (&syn::Variant, Vec<PayloadDefault>) {
+    let mut marked_variants= enum_variants.iter()
+        .filter_map(|variant| variant.attrs.iter().find(|attr| attr.path.is_ident("schema_default")).map(|attr| (variant, attr)));
+
+    let (default_variant, attr)= match marked_variants.next() {
+        Some(marked_variant) => marked_variant,
+        None => {
+            let first_variant= enum_variants.iter().find(|variant| !attributes::parse_attributes(&variant.attrs).other);
+            return (first_variant.unwrap_or(&enum_variants[0]), Vec::new());
+        },
+    };
+    if let Some((variant, _))= marked_variants.next() {
+        panic!("Only one variant can be marked #[schema_default], found {} and {}", default_variant.ident, variant.ident);
+    }
+
+    // Just #[schema_default] uses the default of every field
+    if attr.tokens.is_empty() {
+        return (default_variant, Vec::new());
+    }
+    let payload_defaults= attr.parse_args_with(syn::punctuated::Punctuated::<PayloadDefault, syn::Token![,]>::parse_terminated)
+        .unwrap_or_else(|err| panic!("Unable to parse schema_default attribute on variant {}: {}", default_variant.ident, err));
+    (default_variant, payload_defaults.into_iter().collect())
+}
+
+pub fn derive_default_fn(enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+
+    assert!(enum_variants.len() > 0, "Cannot schematize uninhabitable enum.");
+
+    // Defaults to the variant marked with #[schema_default], so reordering the variants doesn't change it
+    let (default_variant, payload_defaults)= default_variant(enum_variants);
+    let variant_ident= &default_variant.ident;
+
+    // Fields without a #[schema_default(...)] value use their own default, e.g. count: <i32>::schema_default()
+    let fields_default= default_variant.fields.iter().enumerate().map(|(index, field)| {
+        let field_member= struct_derive::field_member(index, field);
+        let field_codec= struct_derive::field_codec(field);
+        match payload_defaults.iter().find(|payload_default| payload_default.member == field_member) {
+            Some(PayloadDefault { value, .. }) => quote! { #field_member: #value },
+            None => quote! { #field_member: #field_codec::schema_default() },
+        }
+    }).collect::<Vec<_>>();
+
+    for payload_default in &payload_defaults {
+        if !default_variant.fields.iter().enumerate()
+            .any(|(index, field)| struct_derive::field_member(index, field) == payload_default.member) {
+            let member= &payload_default.member;
+            panic!("Variant {} has no field {} for its schema_default attribute", variant_ident, quote!(#member));
+        }
+    }
+
+    // The fields of a tuple variant are set by index, e.g. Self::Damage { 0: 2.5, 1: <DamageType>::schema_default() }
+    let variant_construct= match &default_variant.fields {
+        syn::Fields::Unit => quote! { #variant_ident },
+        _ => quote! { #variant_ident { #(#fields_default),* } },
     };
 
     quote! {
-        fn schema_default() -> #enum_ident {
-            #enum_ident::#variant_construct
+        fn schema_default() -> Self {
+            Self::#variant_construct
         }
     }
 }
 
-pub fn derive_serialize_fn(
-    enum_ident: &syn::Ident,
-    enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+pub fn derive_serialize_fn(enum_variants: &EnumVariants, enum_attributes: &attributes::SchemaAttributes)
+    -> proc_macro2::TokenStream {
+    match &enum_attributes.repr {
+        attributes::EnumRepr::External => (),
+        attributes::EnumRepr::Discriminant => return derive_discriminant_serialize_fn(enum_variants),
+        attributes::EnumRepr::Tagged(tag) => return derive_tagged_serialize_fn(tag, enum_variants),
+    }
 
     // Generate map from enum value to string representing enum
     let variants_serialize= enum_variants.iter().map(
         |variant| -> proc_macro2::TokenStream {
-
-            let variant_ident= &variant.ident;
-
-            return match &variant.fields {
-                syn::Fields::Unit => quote! {
-                    #enum_ident::#variant_ident => context.print(stringify!(#variant_ident)),
+            let variant_name= crate::schema_value_name(
+                &crate::schema_name(&variant.ident, &attributes::parse_attributes(&variant.attrs)));
+            let variant_pattern= variant_pattern(variant);
+            let field_bindings= field_bindings(&variant.fields);
+
+            // Variants with fields are written as `Name { ... }`
+            let fields_serialize= match &variant.fields {
+                _ if variant.fields.is_empty() => {
+                    return quote! {
+                        #variant_pattern => context.print(#variant_name),
+                    };
                 },
-                syn::Fields::Unnamed(fields) => {
-                    assert!(fields.unnamed.len() == 1, "Can have max of one field in an enum variant.");
-
+                // The field of the #[schema(other)] variant writes the whole variant, under its original name
+                _ if attributes::parse_attributes(&variant.attrs).other => {
                     return quote! {
-                        #enum_ident::#variant_ident(field) => {
-                            context.print(stringify!(#variant_ident));
-                            context.print(" {");
-
-                            context.tabs+= 1;
-                            context.println();
-                            context.print_tabs();
-                            field.serialize(context);
-                            context.println();
-                            context.tabs-= 1;
-
-                            context.print_tabs();
-                            context.print("}");
-                        },
+                        #variant_pattern => field_0.serialize(context),
+                    };
+                },
+                syn::Fields::Named(fields) => {
+                    let fields_serialize= fields.named.iter().zip(&field_bindings).map(
+                        |(field, field_binding)| struct_derive::generate_field_serialize(field, quote! { #field_binding }));
+                    quote! {
+                        let mut has_fields= false;
+                        #(#fields_serialize)*
+                        context.print_unknown_fields(has_fields);
+                    }
+                },
+                _ if variant.fields.len() == 1 => quote! {
+                    field_0.serialize(context);
+                },
+                _ => {
+                    let fields_serialize= field_bindings.iter().enumerate().map(
+                        |(index, field_binding)| {
+                            let newline= if index == field_bindings.len()-1 {
+                                quote! {}
+                            } else {
+                                quote! {
+                                    context.print(",\n");
+                                    context.print_tabs();
+                                }
+                            };
+                            quote! {
+                                context.path.push(format!("[{}]", #index));
+                                #field_binding.serialize(context);
+                                context.path.pop();
+                                #newline
+                            }
+                        });
+                    quote! {
+                        #(#fields_serialize)*
                     }
                 },
-                syn::Fields::Named(_) => panic!("Named fields in enums are not supported.")
             };
+
+            quote! {
+                #variant_pattern => {
+                    context.print(#variant_name);
+                    context.print(" {");
+
+                    context.tabs+= 1;
+                    context.println();
+                    context.print_tabs();
+                    #fields_serialize
+                    context.println();
+                    context.tabs-= 1;
+
+                    context.print_tabs();
+                    context.print("}");
+                },
+            }
         });
 
     quote! {
@@ -83,47 +278,150 @@ pub fn derive_serialize_fn(
     }
 }
 
-pub fn derive_build_layout_fn(enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+// e.g. `2` for Priority::High
+fn derive_discriminant_serialize_fn(enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+    let variant_idents= enum_variants.iter().map(|variant| &variant.ident).collect::<Vec<_>>();
+
+    quote! {
+        fn serialize(&self, context: &mut SerializeContext) {
+            let discriminant= match self {
+                #(Self::#variant_idents => Self::#variant_idents as i64,)*
+            };
+            context.print(&discriminant.to_string());
+        }
+    }
+}
+
+// e.g. `{ type: "Spawn", count: 3 }`, with the variant's fields after its tag
+fn derive_tagged_serialize_fn(tag: &str, enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+    let variants_serialize= enum_variants.iter().map(
+        |variant| -> proc_macro2::TokenStream {
+            let variant_name= crate::schema_name(&variant.ident, &attributes::parse_attributes(&variant.attrs));
+            let tag_field= format!("{}: {:?}", tag, variant_name);
+            let variant_pattern= variant_pattern(variant);
+            let field_bindings= field_bindings(&variant.fields);
+
+            let fields_serialize= variant.fields.iter().zip(&field_bindings).map(
+                |(field, field_binding)| struct_derive::generate_field_serialize(field, quote! { #field_binding }));
+            let has_fields_init= if variant.fields.is_empty() {
+                quote! { let has_fields= true; }
+            } else {
+                quote! { let mut has_fields= true; }
+            };
+
+            quote! {
+                #variant_pattern => {
+                    context.print("{\n");
+                    context.tabs+= 1;
+                    context.print_tabs();
+                    context.print(#tag_field);
+
+                    #has_fields_init
+                    #(#fields_serialize)*
+                    context.print_unknown_fields(has_fields);
+
+                    context.tabs-= 1;
+                    context.println();
+                    context.print_tabs();
+                    context.print("}");
+                },
+            }
+        });
+
+    quote! {
+        fn serialize(&self, context: &mut SerializeContext) {
+            match self {
+                #(#variants_serialize)*
+            }
+        }
+    }
+}
+
+// A discriminant has no memory to allocate, so only tagged enums build their layout differently
+pub fn derive_build_layout_fn(enum_variants: &EnumVariants, enum_attributes: &attributes::SchemaAttributes)
+    -> proc_macro2::TokenStream {
+    if let attributes::EnumRepr::Tagged(tag)= &enum_attributes.repr {
+        return derive_tagged_build_layout_fn(tag, enum_variants);
+    }
 
     // match Self
     //    case Primary => Ok(layout)
     //    case Tertiary(field) => Ok(i32::build_layout(field, layout, offsets)
 
-    let variants_build_layout= enum_variants.iter().map(
+    let other_variant= other_variant(enum_variants);
+    let variants_build_layout= enum_variants.iter().filter(|variant| matched_by_name(variant)).map(
         |variant| -> proc_macro2::TokenStream {
-            let variant_ident= &variant.ident;
+            let variant_names= crate::schema_input_names(&variant.ident, &attributes::parse_attributes(&variant.attrs));
+            let field_count= variant.fields.len();
 
             let build_layout_variant= match &variant.fields {
-                syn::Fields::Unit => quote! { Ok(layout) },
-                syn::Fields::Unnamed(fields) => {
-                    assert!(fields.unnamed.len() == 1, "Can have max of one field in an enum variant.");
-
+                _ if variant.fields.is_empty() => quote! { Ok(layout) },
+                syn::Fields::Named(fields) => {
+                    let fields_build_layout= fields.named.iter().map(struct_derive::generate_field_build_layout);
+                    quote! {
+                        match &enum_field.kind {
+                            SchemaValueKind::Object(fields_map) => {
+                                #(#fields_build_layout)*
+                                Ok(layout)
+                            },
+                            _ => Ok(layout),
+                        }
+                    }
+                },
+                syn::Fields::Unnamed(fields) if field_count == 1 => {
                     let field_type= &fields.unnamed[0].ty;
 
                     quote! {
-                        Ok(#field_type::build_layout(enum_field, layout, offsets)?)
+                        Ok(<#field_type>::build_layout(enum_field, layout, offsets)?)
+                    }
+                },
+                _ => {
+                    let fields_build_layout= variant.fields.iter().enumerate().map(|(index, field)| {
+                        let field_type= &field.ty;
+                        quote! {
+                            let layout= <#field_type>::build_layout(&vector[#index], layout, offsets)?;
+                        }
+                    });
+                    quote! {
+                        match &enum_field.kind {
+                            // A wrongly sized array isn't deserialized, so nothing is allocated for it
+                            SchemaValueKind::Array(vector) if vector.len() == #field_count => {
+                                #(#fields_build_layout)*
+                                Ok(layout)
+                            },
+                            _ => Ok(layout),
+                        }
                     }
                 },
-                syn::Fields::Named(_) => panic!("Named fields in enums are not supported."),
             };
 
             return quote! {
-                stringify!(#variant_ident) => #build_layout_variant,
+                #(#variant_names)|* => #build_layout_variant,
             }
         }
     );
 
+    let build_layout_other= match other_variant {
+        Some(syn::Variant { fields: syn::Fields::Unnamed(fields), .. }) => {
+            let field_type= &fields.unnamed[0].ty;
+            quote! { <#field_type>::build_layout(schema_value, layout, offsets) }
+        },
+        _ => quote! {
+            {
+                // wrong value, no-op
+                Ok(layout)
+            }
+        },
+    };
+
     quote! {
         fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
             -> Result<alloc::Layout, alloc::LayoutError> {
-            match schema_value {
-                SchemaValue::EnumVariant(enum_name, enum_field) => {
+            match &schema_value.kind {
+                SchemaValueKind::EnumVariant(enum_name, enum_field) => {
                     match *enum_name {
                         #(#variants_build_layout)*
-                        _ => {
-                            // wrong value, no-op
-                            Ok(layout)
-                        }
+                        _ => #build_layout_other,
                     }
                 },
                 _ => {
@@ -135,58 +433,299 @@ pub fn derive_build_layout_fn(enum_variants: &EnumVariants) -> proc_macro2::Toke
     }
 }
 
+fn derive_tagged_build_layout_fn(tag: &str, enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+    let variants_build_layout= enum_variants.iter().filter(|variant| !variant.fields.is_empty()).map(
+        |variant| -> proc_macro2::TokenStream {
+            let variant_names= crate::schema_input_names(&variant.ident, &attributes::parse_attributes(&variant.attrs));
+            let fields_build_layout= variant.fields.iter().map(struct_derive::generate_field_build_layout);
+
+            quote! {
+                #(#variant_names)|* => {
+                    #(#fields_build_layout)*
+                    Ok(layout)
+                },
+            }
+        });
+
+    quote! {
+        fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
+            -> Result<alloc::Layout, alloc::LayoutError> {
+            match &schema_value.kind {
+                SchemaValueKind::Object(fields_map) => match fields_map.get(#tag) {
+                    Some(SchemaValue { kind: SchemaValueKind::String(variant_name), .. }) => match *variant_name {
+                        #(#variants_build_layout)*
+                        _ => Ok(layout),
+                    },
+                    _ => Ok(layout),
+                },
+                _ => {
+                    // wrong value, no-op
+                    Ok(layout)
+                }
+            }
+        }
+    }
+}
+
+// Deserializes a struct-like variant from the object `schema_value`, whose fields are in `fields_map`.
+// The object of a tagged variant also holds its `tag`.
+fn generate_object_variant_deserialize(
+    enum_ident: &syn::Ident,
+    variant: &syn::Variant,
+    enum_attributes: &attributes::SchemaAttributes,
+    tag: Option<&str>,
+) -> proc_macro2::TokenStream {
+    let variant_ident= &variant.ident;
+    let handle_unknown_field= struct_derive::generate_unknown_field_handler(enum_ident, &enum_attributes.unknown_fields);
+    let known_field_names= tag.map(String::from).into_iter().chain(variant.fields.iter().flat_map(
+        |field| crate::schema_input_names(field.ident.as_ref().unwrap(), &attributes::parse_attributes(&field.attrs))
+    ));
+
+    let fields_deserialize= variant.fields.iter().map(|field| {
+        let field_ident= &field.ident;
+        let field_codec= struct_derive::field_codec(field);
+        let optional= enum_attributes.default || attributes::parse_attributes(&field.attrs).default;
+        let field_deserialize= struct_derive::generate_field_deserialize(
+            enum_ident, field, quote! { #field_codec::schema_default() }, optional);
+        quote! {
+            #field_ident: #field_deserialize
+        }
+    });
+
+    quote! {
+        let known_fields: &[&str]= &[#(#known_field_names),*];
+        for field in fields_map.fields().filter(|field| !known_fields.contains(&field.name)) {
+            #handle_unknown_field
+        }
+
+        Self::#variant_ident { #(#fields_deserialize),* }
+    }
+}
+
+// Deserializes a variant name the enum doesn't know, found in `unknown_value`, to the #[schema(other)]
+// variant if there is one
+fn generate_deserialize_other(
+    enum_ident: &syn::Ident,
+    enum_variants: &EnumVariants,
+    unknown_value: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let unknown_variant_error= quote! {
+        context.error(SchemaErrorKind::UnknownIdentifier, #unknown_value)
+            .with_type_name(stringify!(#enum_ident))
+            .with_expected(concat!("a variant of ", stringify!(#enum_ident)))
+    };
+
+    match other_variant(enum_variants) {
+        // The whole variant is deserialized by the field, e.g. an UnknownVariant keeping its name and fields
+        Some(syn::Variant { ident, fields: syn::Fields::Unnamed(fields), .. }) => {
+            let field_type= &fields.unnamed[0].ty;
+            quote! { Self::#ident(<#field_type>::deserialize(#unknown_value, context)?) }
+        },
+        // The variant is lost, so warn about it
+        Some(syn::Variant { ident, .. }) => quote! {
+            {
+                context.warnings.push(#unknown_variant_error);
+                Self::#ident
+            }
+        },
+        None => quote! {
+            {
+                // Could this be an incorrectly spelled enum variant, or one removed from the schema?
+                return Err(#unknown_variant_error);
+            }
+        },
+    }
+}
+
 pub fn derive_deserialize_fn(
     enum_ident: &syn::Ident,
-    enum_variants: &EnumVariants) -> proc_macro2::TokenStream {
+    enum_variants: &EnumVariants,
+    enum_attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+
+    if let attributes::EnumRepr::Tagged(tag)= &enum_attributes.repr {
+        return derive_tagged_deserialize_fn(tag, enum_ident, enum_variants, enum_attributes);
+    }
+
+    let post_load= crate::generate_post_load(enum_ident, enum_attributes);
+    let version_check= crate::generate_version_check(enum_attributes);
 
-    let variants_deserialize= enum_variants.iter().map(
+    let variants_deserialize= enum_variants.iter().filter(|variant| matched_by_name(variant)).map(
         |variant| -> proc_macro2::TokenStream {
             let variant_ident= &variant.ident;
+            let variant_names= crate::schema_input_names(variant_ident, &attributes::parse_attributes(&variant.attrs));
+            let field_count= variant.fields.len();
 
             let deserialize_variant= match &variant.fields {
-                syn::Fields::Unit => quote! {
-                    #enum_ident::#variant_ident
-                },
-                syn::Fields::Unnamed(fields) => {
-                    assert!(fields.unnamed.len() == 1, "Can have max of one field in an enum variant.");
+                // The pattern also constructs it, e.g. `Self::Empty {}`
+                _ if variant.fields.is_empty() => variant_pattern(variant),
+                syn::Fields::Named(_) => {
+                    let object_variant_deserialize= generate_object_variant_deserialize(
+                        enum_ident, variant, enum_attributes, None);
 
+                    quote! {
+                        match &enum_field.kind {
+                            SchemaValueKind::Object(fields_map) => {
+                                // The fields are read from the variant's object, e.g. `Spawn { count: 3 }`
+                                let schema_value: &SchemaValue= enum_field;
+                                #object_variant_deserialize
+                            },
+                            _ => {
+                                return Err(context.error(SchemaErrorKind::WrongSchemaValue, enum_field)
+                                    .with_type_name(stringify!(#enum_ident))
+                                    .with_expected("Object"));
+                            }
+                        }
+                    }
+                },
+                syn::Fields::Unnamed(fields) if field_count == 1 => {
                     let field_type= &fields.unnamed[0].ty;
 
                     quote! {
-                        #enum_ident::#variant_ident(#field_type::deserialize(enum_field, context)?)
+                        Self::#variant_ident(deserialize_or_default::<#field_type>(enum_field, context)?)
+                    }
+                },
+                _ => {
+                    // As with array elements, a field that fails to deserialize is recorded in the context
+                    // and replaced with its default value
+                    let fields_deserialize= variant.fields.iter().enumerate().map(|(index, field)| {
+                        let field_type= &field.ty;
+                        quote! {
+                            {
+                                let field_value= &vector[#index];
+                                context.path.push(format!("[{}]", #index));
+                                let offset_index= context.offset_index;
+                                let value= match deserialize_or_default::<#field_type>(field_value, context) {
+                                    Ok(value) => value,
+                                    Err(error) => context.recover(error, field_value, offset_index),
+                                };
+                                context.path.pop();
+                                value
+                            }
+                        }
+                    });
+
+                    quote! {
+                        match &enum_field.kind {
+                            SchemaValueKind::Array(vector) if vector.len() == #field_count => {
+                                Self::#variant_ident(#(#fields_deserialize),*)
+                            },
+                            SchemaValueKind::Array(_) => {
+                                return Err(context.error(SchemaErrorKind::WrongSizedArray, enum_field)
+                                    .with_type_name(stringify!(#enum_ident))
+                                    .with_expected(format!("{} values", #field_count)));
+                            },
+                            _ => {
+                                return Err(context.error(SchemaErrorKind::WrongSchemaValue, enum_field)
+                                    .with_type_name(stringify!(#enum_ident))
+                                    .with_expected(format!("{} values", #field_count)));
+                            }
+                        }
                     }
                 },
-                syn::Fields::Named(_) => panic!("Named fields in enums are not supported.")
             };
 
             return quote! {
-                stringify!(#variant_ident) => #deserialize_variant,
+                #(#variant_names)|* => #deserialize_variant,
             }
         });
 
+    let deserialize_other= generate_deserialize_other(enum_ident, enum_variants, quote! { schema_value });
 
+    // Discriminant enums also read their variant names, so only a number needs handling of its own
+    let (deserialize_discriminant, expected)= match enum_attributes.repr {
+        attributes::EnumRepr::Discriminant => {
+            let variant_idents= enum_variants.iter().map(|variant| &variant.ident).collect::<Vec<_>>();
+            let deserialize_discriminant= quote! {
+                SchemaValueKind::Integer(discriminant) => match *discriminant {
+                    #(discriminant if discriminant == Self::#variant_idents as i64 => Self::#variant_idents,)*
+                    _ => #deserialize_other,
+                },
+            };
+            (deserialize_discriminant, "EnumVariant or Integer")
+        },
+        _ => (quote! {}, "EnumVariant"),
+    };
 
     quote! {
-        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<#enum_ident> {
-            Ok(match schema_value {
-                SchemaValue::EnumVariant(enum_name, enum_field) =>
+        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> {
+            #version_check
+            let error_count= context.errors.len();
+            let mut value= match &schema_value.kind {
+                SchemaValueKind::EnumVariant(enum_name, enum_field) =>
                     match *enum_name {
                         #(#variants_deserialize)*
-                        _ => {
-                            println!("Deserialize hit an unexpected identifier for field '{}'. Expected: EnumVariant, found: {}.",
-                                context.get_path(),
-                                enum_name);
-                            println!("Could this be incorrectly spelled enum variant or removed from the new schema?");
-                            return Err(SchemaError::UnknownIdentifier);
-                        }
+                        _ => #deserialize_other,
                     },
+                #deserialize_discriminant
                 _ => {
-                    println!("Deserialize hit a wrong value for field '{}'. Expected: EnumVariant, found: {:?}",
-                        context.get_path(),
-                        schema_value);
-                    return Err(SchemaError::WrongSchemaValue);
+                    return Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                        .with_type_name(stringify!(#enum_ident))
+                        .with_expected(#expected));
                 }
-            })
+            };
+            #post_load
+            Ok(value)
+        }
+    }
+}
+
+fn derive_tagged_deserialize_fn(
+    tag: &str,
+    enum_ident: &syn::Ident,
+    enum_variants: &EnumVariants,
+    enum_attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+
+    let post_load= crate::generate_post_load(enum_ident, enum_attributes);
+    let version_check= crate::generate_version_check(enum_attributes);
+
+    let variants_deserialize= enum_variants.iter().filter(|variant| matched_by_name(variant)).map(
+        |variant| -> proc_macro2::TokenStream {
+            let variant_names= crate::schema_input_names(&variant.ident, &attributes::parse_attributes(&variant.attrs));
+            let object_variant_deserialize= generate_object_variant_deserialize(
+                enum_ident, variant, enum_attributes, Some(tag));
+
+            quote! {
+                #(#variant_names)|* => {
+                    #object_variant_deserialize
+                },
+            }
+        });
+
+    let deserialize_other= generate_deserialize_other(enum_ident, enum_variants, quote! { &fields_map[#tag] });
+
+    quote! {
+        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> {
+            #version_check
+            let error_count= context.errors.len();
+            let mut value= match &schema_value.kind {
+                // The variant is named by a field of its object, e.g. `{ type: "Spawn", count: 3 }`
+                SchemaValueKind::Object(fields_map) => {
+                    context.path.push(format!(".{}", #tag));
+                    let variant_name= match fields_map.get(#tag) {
+                        Some(SchemaValue { kind: SchemaValueKind::String(variant_name), .. }) => Ok(*variant_name),
+                        Some(tag_value) => Err(context.error(SchemaErrorKind::WrongSchemaValue, tag_value)
+                            .with_type_name(stringify!(#enum_ident))
+                            .with_expected("String")),
+                        None => Err(SchemaError::new(SchemaErrorKind::MissingField)
+                            .with_path(context.get_path())
+                            .with_type_name(stringify!(#enum_ident))
+                            .with_span(schema_value.span)),
+                    };
+                    context.path.pop();
+
+                    match variant_name? {
+                        #(#variants_deserialize)*
+                        _ => #deserialize_other,
+                    }
+                },
+                _ => {
+                    return Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                        .with_type_name(stringify!(#enum_ident))
+                        .with_expected("Object"));
+                }
+            };
+            #post_load
+            Ok(value)
         }
     }
 }
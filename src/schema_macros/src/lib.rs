@@ -9,61 +9,222 @@
        - generates a schematized object representation of the object
       deserialize
        - deserializes the schematized object into an instance of the item
+
+    Items and fields can be configured with #[schema(...)] markup, see attributes.rs
 */
 
 extern crate proc_macro;
 extern crate proc_macro2;
 
+mod attributes;
 mod struct_derive;
+mod tuple_derive;
+mod transparent_derive;
 mod enum_derive;
 
 use quote::quote;
+use syn::ext::IdentExt;
+
+// The name of a field or variant as written in a definition file. Raw identifiers are written
+// without their prefix, e.g. the field `r#type` is written as `type: ...`, unless it is renamed
+// with #[schema(rename = "...")]
+fn schema_name(ident: &syn::Ident, attributes: &attributes::SchemaAttributes) -> String {
+    match &attributes.rename {
+        Some(rename) => rename.clone(),
+        None => ident.unraw().to_string(),
+    }
+}
+
+// Every name accepted when reading a field or variant: its schema name, then any #[schema(alias = "...")]
+fn schema_input_names(ident: &syn::Ident, attributes: &attributes::SchemaAttributes) -> Vec<String> {
+    let mut names= vec![schema_name(ident, attributes)];
+    names.extend(attributes.aliases.iter().cloned());
+    names
+}
+
+// The name of an enum variant when written as a value. Variants which collide with a value keyword
+// must be written as raw identifiers, otherwise `true` would be read back as a bool.
+fn schema_value_name(name: &str) -> String {
+    match name {
+        "true" | "false" | "inf" | "nan" | "default" => format!("r#{}", name),
+        _ => String::from(name),
+    }
+}
+
+// Calls the #[schema(post_load = ...)] hook on the deserialized `value`. Hooks run bottom-up, since nested
+// values are deserialized first, and only if the value deserialized without errors (`error_count` is the
+// number of errors before it started), so a hook never sees fields which were replaced by their defaults.
+fn generate_post_load(item_ident: &syn::Ident, attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+    match &attributes.post_load {
+        Some(post_load) => quote! {
+            if context.errors.len() == error_count {
+                if let Err(errors)= #post_load(&mut value) {
+                    context.post_load_errors(errors, schema_value, stringify!(#item_ident));
+                }
+            }
+        },
+        None => quote! {},
+    }
+}
+
+// A #[schema(version = N)] type checks that it's the root of the definition file, the only value whose
+// version is read and migrated
+fn generate_version_check(attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+    match attributes.version {
+        Some(_) => quote! {
+            context.check_versioned_root::<Self>(schema_value)?;
+        },
+        None => quote! {},
+    }
+}
+
+// The generics of the Schematize impl: the item's own, with a `T: Schematize` bound on every type parameter,
+// or the bounds given by #[schema(bound = "...")] instead
+fn schema_generics(generics: &syn::Generics, attributes: &attributes::SchemaAttributes) -> syn::Generics {
+    let bounds: Vec<syn::WherePredicate>= match &attributes.bound {
+        Some(bound) => bound.clone(),
+        None => generics.type_params().map(|param| {
+            let ident= &param.ident;
+            syn::parse_quote! { #ident: Schematize }
+        }).collect(),
+    };
+
+    let mut generics= generics.clone();
+    generics.make_where_clause().predicates.extend(bounds);
+    generics
+}
 
-#[proc_macro_derive(Schematize, attributes(schema_default))]
+// Every name a field or variant is read by must belong to it alone, counting #[schema(rename)] and
+// #[schema(alias)], otherwise two of them would be read from the same value and written under the same name
+fn check_unique_names<'a>(kind: &str, items: impl Iterator<Item= (&'a syn::Ident, Vec<String>)>) {
+    let mut seen_names: Vec<(&syn::Ident, String)>= Vec::new();
+    for (ident, names) in items {
+        for name in names {
+            if let Some((seen_ident, _))= seen_names.iter().find(|(_, seen_name)| *seen_name == name) {
+                panic!("{} {} and {} are both named {} in the definition file", kind, seen_ident, ident, name);
+            }
+            seen_names.push((ident, name));
+        }
+    }
+}
+
+#[proc_macro_derive(Schematize, attributes(schema_default, schema))]
 pub fn derive_schematize_impl(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // The abstract syntax tree representing the parsed item
     let item_ast: syn::DeriveInput= syn::parse_macro_input!(item);
+    derive_schematize(item_ast).into()
+}
+
+fn derive_schematize(item_ast: syn::DeriveInput) -> proc_macro2::TokenStream {
     let item_ident= &item_ast.ident;
+    let item_attributes= attributes::parse_attributes(&item_ast.attrs);
+    let generics= schema_generics(&item_ast.generics, &item_attributes);
+    let (impl_generics, type_generics, where_clause)= generics.split_for_impl();
+
+    let version_const= match item_attributes.version {
+        Some(version) => quote! { const VERSION: u32= #version; },
+        None => quote! {},
+    };
 
     // Generate the token stream for the schema implementation of this item.
     match item_ast.data {
-        syn::Data::Struct(data_struct) =>
-            match data_struct.fields {
-                syn::Fields::Named(fields_named) => {
-                    let fields= fields_named.named;
-
-                    // Generate the Schematize implementation for this struct
-                    let struct_schema_default_fn= struct_derive::derive_default_fn(item_ident, &fields);
-                    let struct_serialize_fn= struct_derive::derive_serialize_fn(&fields);
-                    let struct_build_layout_fn= struct_derive::derive_build_layout_fn(&fields);
-                    let struct_deserialize_fn= struct_derive::derive_deserialize_fn(item_ident, &fields);
-
-                    let schematize_impl= quote! {
-                        impl Schematize for #item_ident {
-                            #struct_schema_default_fn
-                            #struct_serialize_fn
-                            #struct_build_layout_fn
-                            #struct_deserialize_fn
-                        }
-                    };
-
-                    //println!("{}", schematize_impl);
-
-                    schematize_impl.into()
-                },
-                _ => unimplemented!("Schematize only supports named struct fields"),
+        syn::Data::Struct(data_struct) => {
+            // Tuple structs are written as an array of their fields, and unit structs as an object without
+            // any fields, e.g. `marker: {}`
+            let is_tuple= matches!(data_struct.fields, syn::Fields::Unnamed(_));
+            let fields= match data_struct.fields {
+                syn::Fields::Named(fields_named) => fields_named.named,
+                syn::Fields::Unnamed(fields_unnamed) => fields_unnamed.unnamed,
+                syn::Fields::Unit => struct_derive::StructFields::new(),
+            };
+
+            if !is_tuple && !item_attributes.transparent {
+                let named_fields= fields.iter().filter(|field| {
+                    let field_attributes= attributes::parse_attributes(&field.attrs);
+                    !field_attributes.skip && !field_attributes.flatten
+                });
+                check_unique_names("Fields", named_fields.map(|field| {
+                    let field_ident= field.ident.as_ref().unwrap();
+                    (field_ident, schema_input_names(field_ident, &attributes::parse_attributes(&field.attrs)))
+                }));
             }
+
+            // The types of flattened fields must be structs which can be flattened too
+            let mut generics= generics.clone();
+            if !is_tuple && !item_attributes.transparent {
+                generics.make_where_clause().predicates.extend(struct_derive::flatten_bounds(&fields));
+            }
+            let (impl_generics, type_generics, where_clause)= generics.split_for_impl();
+
+            // Structs with named fields can themselves be flattened. A transparent struct can't be, even if its
+            // field could: there's no telling from its type, and a bound such as `f32: SchemaFlatten` doesn't compile.
+            let flatten_impl= if is_tuple || item_attributes.transparent {
+                quote! {}
+            } else {
+                quote! {
+                    impl #impl_generics SchemaFlatten for #item_ident #type_generics #where_clause {}
+                }
+            };
+
+            // Generate the Schematize implementation for this struct
+            let struct_schema_default_fn= struct_derive::derive_default_fn(&fields);
+            let (struct_serialize_fn, struct_build_layout_fn, struct_deserialize_fn, struct_field_names_fn)=
+                if item_attributes.transparent {
+                    (transparent_derive::derive_serialize_fn(&fields),
+                     transparent_derive::derive_build_layout_fn(&fields),
+                     transparent_derive::derive_deserialize_fn(item_ident, &fields, &item_attributes),
+                     quote! {})
+                } else if is_tuple {
+                    (tuple_derive::derive_serialize_fn(&fields),
+                     tuple_derive::derive_build_layout_fn(&fields),
+                     tuple_derive::derive_deserialize_fn(item_ident, &fields, &item_attributes),
+                     quote! {})
+                } else {
+                    (struct_derive::derive_serialize_fn(&fields, &item_attributes),
+                     struct_derive::derive_build_layout_fn(&fields),
+                     struct_derive::derive_deserialize_fn(item_ident, &fields, &item_attributes),
+                     struct_derive::derive_field_names_fn(&fields))
+                };
+
+            let schematize_impl= quote! {
+                impl #impl_generics Schematize for #item_ident #type_generics #where_clause {
+                    #version_const
+                    #struct_schema_default_fn
+                    #struct_serialize_fn
+                    #struct_build_layout_fn
+                    #struct_deserialize_fn
+                    #struct_field_names_fn
+                }
+
+                #flatten_impl
+            };
+
+            //println!("{}", schematize_impl);
+
+            schematize_impl
+        }
         syn::Data::Enum(data_enum) => {
             let variants= &data_enum.variants;
+            enum_derive::check_variants(variants, &item_attributes);
+
+            check_unique_names("Variants", variants.iter()
+                .map(|variant| (&variant.ident, schema_input_names(&variant.ident, &attributes::parse_attributes(&variant.attrs)))));
+            for variant in variants.iter().filter(|variant| matches!(variant.fields, syn::Fields::Named(_))) {
+                check_unique_names("Fields", variant.fields.iter().map(|field| {
+                    let field_ident= field.ident.as_ref().unwrap();
+                    (field_ident, schema_input_names(field_ident, &attributes::parse_attributes(&field.attrs)))
+                }));
+            }
 
             // Generate the Schematize implementation for this struct
-            let enum_schema_default_fn= enum_derive::derive_default_fn(item_ident, &variants);
-            let enum_serialize_fn= enum_derive::derive_serialize_fn(item_ident, &variants);
-            let enum_build_layout_fn = enum_derive::derive_build_layout_fn(&variants);
-            let enum_deserialize_fn = enum_derive::derive_deserialize_fn(item_ident, &variants);
+            let enum_schema_default_fn= enum_derive::derive_default_fn(&variants);
+            let enum_serialize_fn= enum_derive::derive_serialize_fn(&variants, &item_attributes);
+            let enum_build_layout_fn = enum_derive::derive_build_layout_fn(&variants, &item_attributes);
+            let enum_deserialize_fn = enum_derive::derive_deserialize_fn(item_ident, &variants, &item_attributes);
 
             let schematize_impl= quote! {
-                impl Schematize for #item_ident {
+                impl #impl_generics Schematize for #item_ident #type_generics #where_clause {
+                    #version_const
                     #enum_schema_default_fn
                     #enum_serialize_fn
                     #enum_build_layout_fn
@@ -71,10 +232,112 @@ pub fn derive_schematize_impl(item: proc_macro::TokenStream) -> proc_macro::Toke
                 }
             };
 
-            println!("{}", schematize_impl);
+            //println!("{}", schematize_impl);
 
-            schematize_impl.into()
+            schematize_impl
         }
         _ => unimplemented!("Schematize only supports structs & enums")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Derives Schematize for the item, to check the markup errors that are reported at compile time
+    fn derive(item: &str) -> proc_macro2::TokenStream {
+        derive_schematize(syn::parse_str(item).expect("Unable to parse test item"))
+    }
+
+    #[test]
+    #[should_panic(expected= "Fields a and b are both named a in the definition file")]
+    fn renamed_field_collision() {
+        derive("struct Item { a: i32, #[schema(rename = \"a\")] b: i32 }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Fields a and b are both named old in the definition file")]
+    fn aliased_field_collision() {
+        derive("struct Item { #[schema(alias = \"old\")] a: i32, #[schema(alias = \"old\")] b: i32 }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Variants First and Second are both named First in the definition file")]
+    fn aliased_variant_collision() {
+        derive("enum Item { First, #[schema(alias = \"First\")] Second }");
+    }
+
+    #[test]
+    fn unique_names() {
+        // A skipped field isn't in the definition file, so its name is free
+        derive("struct Item { #[schema(rename = \"b\")] a: i32, #[schema(skip)] b: i32 }");
+        derive("enum Item { #[schema(rename = \"Second\")] First, #[schema(rename = \"First\")] Second }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Only one variant can be marked #[schema_default], found First and Second")]
+    fn default_variant_marked_twice() {
+        derive("enum Item { #[schema_default] First, #[schema_default] Second }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Variant Spawn has no field delay for its schema_default attribute")]
+    fn default_variant_unknown_field() {
+        derive("enum Item { Clear, #[schema_default(delay = 0.5)] Spawn { count: i32 } }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Unable to parse schema_default attribute on variant Spawn: unexpected end of input, expected a value after '='")]
+    fn default_variant_missing_value() {
+        derive("enum Item { #[schema_default(count =)] Spawn { count: i32 } }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Schema attribute tag requires unit or struct-like variants, found unnamed fields on variant A")]
+    fn tagged_tuple_variant() {
+        derive("#[schema(tag = \"type\")] enum Item { A(i32), B }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Schema attribute discriminant requires an enum without fields, found fields on variant B")]
+    fn discriminant_with_fields() {
+        derive("#[schema(discriminant)] enum Item { A = 1, B { count: i32 } }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Variant A has a field named type, which is the tag of the enum")]
+    fn tagged_field_named_tag() {
+        derive("#[schema(tag = \"type\")] enum Item { A { #[schema(rename = \"type\")] kind: i32 } }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Only one variant can be marked #[schema(other)], found A and B")]
+    fn other_variant_marked_twice() {
+        derive("enum Item { #[schema(other)] A, #[schema(other)] B }");
+    }
+
+    #[test]
+    #[should_panic(expected= "The #[schema(other)] variant A must be a unit variant or have a single unnamed field")]
+    fn other_variant_with_named_fields() {
+        derive("enum Item { #[schema(other)] A { name: SchemaString }, B }");
+    }
+
+    #[test]
+    #[should_panic(expected= "Schema attribute tag requires a unit #[schema(other)] variant, found a field on variant Unknown")]
+    fn tagged_other_variant_with_field() {
+        derive("#[schema(tag = \"type\")] enum Item { A { count: i32 }, #[schema(other)] Unknown(UnknownVariant) }");
+    }
+
+    // A flattened field's type must be a struct that can be flattened, otherwise the derive doesn't compile
+    #[test]
+    fn flatten_bounds() {
+        let derived= derive("struct Item { #[schema(flatten)] transform: Transform, kind: Kind }").to_string();
+        assert!(derived.contains("where Transform : SchemaFlatten"), "{}", derived);
+        assert!(!derived.contains("Kind : SchemaFlatten"), "{}", derived);
+        assert!(derived.contains("impl SchemaFlatten for Item"), "{}", derived);
+
+        // Tuple structs are written as arrays, and a transparent struct is written as its field
+        assert!(!derive("struct Item(i32, i32);").to_string().contains("SchemaFlatten"));
+        assert!(!derive("#[schema(transparent)] struct Item(Transform);").to_string().contains("SchemaFlatten"));
+    }
+}
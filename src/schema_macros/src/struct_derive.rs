@@ -2,54 +2,181 @@ extern crate proc_macro2;
 
 use quote::quote;
 
-type StructFields= syn::punctuated::Punctuated<syn::Field, syn::token::Comma>;
+use crate::attributes;
 
-// Cast to an explicit enum type, panicking if it fails.
-macro_rules! cast {
-    ($target: expr, $pat: path) => {
-        {
-            if let $pat(a) = $target {
-                a
-            } else {
-                panic!(
-                    "mismatch variant when cast to {}",
-                    stringify!($pat));
-            }
-        }
-    };
-}
+pub type StructFields= syn::punctuated::Punctuated<syn::Field, syn::token::Comma>;
 
 fn generate_default_value(field_type: &syn::Type) -> proc_macro2::TokenStream {
     match field_type
     {
         syn::Type::Array(array) => {
+            // The length may be a const generic parameter, e.g. [T; N]
             let default_value= generate_default_value(&array.elem);
-            let size= cast!(&cast!(&array.len, syn::Expr::Lit).lit, syn::Lit::Int);
+            let size= &array.len;
             return quote! {
                 [#default_value; #size]
             }
         }
         _ =>
             return quote! {
-                #field_type::schema_default()
+                <#field_type>::schema_default()
             }
     }
 }
 
+// How a field is accessed, by name or by its index in a tuple struct, e.g. `self.x` or `self.0`
+pub fn field_member(index: usize, field: &syn::Field) -> syn::Member {
+    match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(syn::Index::from(index)),
+    }
+}
 
-pub fn derive_default_fn(
-    item_ident: &syn::Ident,
-    fields: &StructFields
-) -> proc_macro2::TokenStream {
+// The path that schematizes a field: its #[schema(with = ...)] codec, otherwise its own type
+pub fn field_codec(field: &syn::Field) -> proc_macro2::TokenStream {
+    let field_type= &field.ty;
+    match attributes::parse_attributes(&field.attrs).with {
+        Some(with) => quote! { #with },
+        None => quote! { <#field_type> },
+    }
+}
+
+// The fields which are written to the definition file, i.e. not marked #[schema(skip)]
+fn schema_fields(fields: &StructFields) -> Vec<&syn::Field> {
+    fields.iter().filter(|field| !attributes::parse_attributes(&field.attrs).skip).collect()
+}
+
+// The names read from the object for the fields of this struct, not counting flattened fields
+fn field_input_names(fields: &StructFields) -> Vec<String> {
+    schema_fields(fields).into_iter()
+        .filter(|field| !attributes::parse_attributes(&field.attrs).flatten)
+        .flat_map(|field| crate::schema_input_names(field.ident.as_ref().unwrap(), &attributes::parse_attributes(&field.attrs)))
+        .collect()
+}
+
+// The types of the #[schema(flatten)] fields of this struct
+fn flattened_field_types(fields: &StructFields) -> Vec<&syn::Type> {
+    schema_fields(fields).into_iter()
+        .filter(|field| attributes::parse_attributes(&field.attrs).flatten)
+        .map(|field| &field.ty)
+        .collect()
+}
+
+// Every #[schema(flatten)] field must have a struct type which can be flattened, e.g. `Transform: SchemaFlatten`.
+// The bound has the span of the field's type, so that's where a type which can't be flattened is reported.
+pub fn flatten_bounds(fields: &StructFields) -> Vec<syn::WherePredicate> {
+    flattened_field_types(fields).into_iter()
+        .map(|field_type| syn::parse_quote_spanned! { syn::spanned::Spanned::span(field_type)=> #field_type: SchemaFlatten })
+        .collect()
+}
+
+// Looks up a field in `fields_map` by its schema name, then by any of its aliases
+fn generate_field_lookup(field: &syn::Field) -> proc_macro2::TokenStream {
+    let field_attributes= attributes::parse_attributes(&field.attrs);
+    let field_name= crate::schema_name(field.ident.as_ref().unwrap(), &field_attributes);
+    let aliases= &field_attributes.aliases;
+
+    quote! {
+        fields_map.get(#field_name)#(.or_else(|| fields_map.get(#aliases)))*
+    }
+}
+
+// Describes bounds for an error message, e.g. `a value between 1 and 10`
+fn generate_bounds_description(description: &str, bounds: &attributes::Bounds) -> proc_macro2::TokenStream {
+    match (&bounds.min, &bounds.max) {
+        (Some(min), Some(max)) => quote! { format!("{} between {} and {}", #description, #min, #max) },
+        (Some(min), None) => quote! { format!("{} of at least {}", #description, #min) },
+        (None, Some(max)) => quote! { format!("{} of at most {}", #description, #max) },
+        (None, None) => quote! { String::from(#description) },
+    }
+}
+
+// The condition for a value outside of the bounds, e.g. `!(1..=10).contains(&value)`
+fn generate_out_of_bounds(value: proc_macro2::TokenStream, bounds: &attributes::Bounds) -> proc_macro2::TokenStream {
+    match (&bounds.min, &bounds.max) {
+        (Some(min), Some(max)) => quote! { !(#min..=#max).contains(&#value) },
+        (Some(min), None) => quote! { #value < #min },
+        (None, Some(max)) => quote! { #value > #max },
+        (None, None) => quote! { false },
+    }
+}
+
+// Generates the checks for any validation markup on a field, e.g. #[schema(range(min = 0), non_empty)].
+// They run on the deserialized `value`, and every failure is recorded as an error.
+pub fn generate_validation(item_ident: &syn::Ident, field_attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+    let mut checks= Vec::new();
+
+    // Builds the error for a failed check, e.g. `expected a value between 1 and 10, found integer 12`
+    let validation_error= |expected: proc_macro2::TokenStream, found: proc_macro2::TokenStream| quote! {
+        let error= context.error(SchemaErrorKind::Validation, field_value)
+            .with_type_name(stringify!(#item_ident))
+            .with_expected(#expected)
+            .with_found(#found);
+        context.errors.push(error);
+    };
+
+    if let Some(range)= &field_attributes.range {
+        let failed= generate_out_of_bounds(quote! { value }, range);
+        let error= validation_error(generate_bounds_description("a value", range), quote! { field_value.kind.describe() });
+        checks.push(quote! {
+            if #failed {
+                #error
+            }
+        });
+    }
+
+    if let Some(len)= &field_attributes.len {
+        let failed= generate_out_of_bounds(quote! { value.len() }, len);
+        let error= validation_error(generate_bounds_description("a length", len), quote! { format!("length {}", value.len()) });
+        checks.push(quote! {
+            if #failed {
+                #error
+            }
+        });
+    }
+
+    if field_attributes.non_empty {
+        let error= validation_error(quote! { "a non-empty value" }, quote! { field_value.kind.describe() });
+        checks.push(quote! {
+            if value.is_empty() {
+                #error
+            }
+        });
+    }
+
+    // Custom predicates return a description of what's wrong with the value
+    for validate in &field_attributes.validate {
+        checks.push(quote! {
+            if let Err(message)= #validate(&value) {
+                let error= SchemaError::new(SchemaErrorKind::Validation)
+                    .with_path(context.get_path())
+                    .with_type_name(stringify!(#item_ident))
+                    .with_found(message)
+                    .with_span(field_value.span);
+                context.errors.push(error);
+            }
+        });
+    }
+
+    quote! { #(#checks)* }
+}
+
+pub fn derive_default_fn(fields: &StructFields) -> proc_macro2::TokenStream {
 
     // Generate the token stream for initializing the default struct
     //  e.g. x: 0, y: 0.0, points: [0,0,0]
-    let fields_init_default= fields.iter().map(
-        |field| -> proc_macro2::TokenStream {
-            let field_ident= &field.ident;
-            let schema_default_value= generate_default_value(&field.ty);
+    // Tuple structs are initialized by index, e.g. Point { 0: 0, 1: 0 }
+    let fields_init_default= fields.iter().enumerate().map(
+        |(index, field)| -> proc_macro2::TokenStream {
+            let field_member= field_member(index, field);
+            let field_attributes= attributes::parse_attributes(&field.attrs);
+            let schema_default_value= match (field_attributes.skip_value, field_attributes.with) {
+                (Some(skip_value), _) => quote! { #skip_value },
+                (None, Some(with)) => quote! { #with::schema_default() },
+                (None, None) => generate_default_value(&field.ty),
+            };
             return quote! {
-                #field_ident : #schema_default_value
+                #field_member : #schema_default_value
             }
         });
 
@@ -74,9 +201,9 @@ pub fn derive_default_fn(
             ).flatten();
 
     quote! {
-        fn schema_default() -> #item_ident {
-            // Create a default, zero-ed out #item_ident.
-            let mut schema_default= #item_ident { #(#fields_init_default),* };
+        fn schema_default() -> Self {
+            // Create a default, zero-ed out item.
+            let mut schema_default= Self { #(#fields_init_default),* };
 
             // Set any overrides specified by schema_default markup
             #(schema_default.#fields_schema_default;)*
@@ -86,64 +213,119 @@ pub fn derive_default_fn(
     }
 }
 
-pub fn derive_serialize_fn(fields: &StructFields) -> proc_macro2::TokenStream {
-    // Generate the token stream for building the field map
-    let fields_serialize= fields.iter().enumerate().map(
-        |(index, field)| -> proc_macro2::TokenStream {
-            let field_ident= &field.ident;
+// Writes a named field, e.g. `speed: 1.5`, after a separator if any fields were written before it.
+// `value` is a reference to the field's value.
+pub fn generate_field_serialize(field: &syn::Field, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let field_name= crate::schema_name(field.ident.as_ref().unwrap(), &attributes::parse_attributes(&field.attrs));
+    let field_codec= field_codec(field);
 
-            let newline;
-            if index == fields.len()-1 {
-                newline= quote! {};
-            } else {
-                newline= quote! {
-                    context.print(",\n");
-                    context.print_tabs();
+    quote! {
+        if has_fields {
+            context.print(",\n");
+            context.print_tabs();
+        }
+        context.print(&format!("{}: ", #field_name));
+        context.path.push(format!(".{}", #field_name));
+        #field_codec::serialize(#value, context);
+        context.path.pop();
+        has_fields= true;
+    }
+}
+
+pub fn derive_serialize_fn(fields: &StructFields, item_attributes: &attributes::SchemaAttributes) -> proc_macro2::TokenStream {
+    let fields= schema_fields(fields);
+
+    // e.g. a unit struct, unless it could have collected unknown fields to write back out
+    if fields.is_empty() && item_attributes.unknown_fields != attributes::UnknownFields::Collect {
+        return quote! {
+            fn serialize(&self, context: &mut SerializeContext) {
+                if !std::mem::take(&mut context.flattened) {
+                    context.print("{}");
                 }
             }
+        };
+    }
 
-            return quote! {
-                context.print(&format!("{}: ", stringify!(#field_ident)));
-                self.#field_ident.serialize(context);
-                #newline
+    // Generate the token stream for building the field map
+    // Fields are separated at runtime, since a flattened field may not write any
+    let fields_serialize= fields.iter().map(
+        |field| -> proc_macro2::TokenStream {
+            let field_ident= &field.ident;
+            let field_attributes= attributes::parse_attributes(&field.attrs);
+            if field_attributes.flatten {
+                return quote! {
+                    has_fields= context.print_flattened(&self.#field_ident, has_fields);
+                };
             }
+
+            generate_field_serialize(field, quote! { &self.#field_ident })
         });
+    let has_fields_init= if fields.is_empty() {
+        quote! { let has_fields= false; }
+    } else {
+        quote! { let mut has_fields= false; }
+    };
 
     quote! {
         fn serialize(&self, context: &mut SerializeContext) {
-            context.print("{\n");
-            context.tabs+= 1;
-            context.print_tabs();
+            // A flattened struct writes its fields into the object of the enclosing struct
+            let flattened= std::mem::take(&mut context.flattened);
+            if !flattened {
+                context.print("{\n");
+                context.tabs+= 1;
+                context.print_tabs();
+            }
 
+            #has_fields_init
             #(#fields_serialize)*
 
-            context.tabs-= 1;
-            context.println();
-            context.print_tabs();
-            context.print("}");
+            if !flattened {
+                context.print_unknown_fields(has_fields);
+
+                context.tabs-= 1;
+                context.println();
+                context.print_tabs();
+                context.print("}");
+            }
         }
     }
 }
 
+// Builds the layout of a named field in `fields_map`, if it was written
+pub fn generate_field_build_layout(field: &syn::Field) -> proc_macro2::TokenStream {
+    let field_lookup= generate_field_lookup(field);
+    let field_codec= field_codec(field);
+
+    quote! {
+        let layout= match #field_lookup {
+            Some(schema_value) => #field_codec::build_layout(schema_value, layout, offsets)?,
+            None => layout,
+        };
+    }
+}
+
 pub fn derive_build_layout_fn(
     fields: &StructFields,
 ) -> proc_macro2::TokenStream {
-    let fields_build_layout= fields.iter().map (
+    let fields_build_layout= schema_fields(fields).into_iter().map (
         |field| -> proc_macro2::TokenStream {
-            let field_ident= &field.ident;
-            let field_type= &field.ty;
-            return quote! {
-                let schema_value= fields_map.get(stringify!(#field_ident)).unwrap_or(&SchemaValue::Null);
-                let layout= <#field_type>::build_layout(schema_value, layout, offsets)?;
+            if attributes::parse_attributes(&field.attrs).flatten {
+                // Reads its fields from the same object
+                let field_type= &field.ty;
+                return quote! {
+                    let layout= <#field_type>::build_layout(schema_value, layout, offsets)?;
+                };
             }
+
+            generate_field_build_layout(field)
         }
     );
 
     quote! {
         fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
             -> Result<alloc::Layout, alloc::LayoutError> {
-            match schema_value {
-                SchemaValue::Object(fields_map) => {
+            match &schema_value.kind {
+                SchemaValueKind::Object(fields_map) => {
                     #(#fields_build_layout)*
 
                     Ok(layout)
@@ -158,63 +340,237 @@ pub fn derive_build_layout_fn(
 
 pub fn derive_deserialize_fn(
     item_ident: &syn::Ident,
-    fields: &StructFields
+    fields: &StructFields,
+    item_attributes: &attributes::SchemaAttributes,
 ) -> proc_macro2::TokenStream {
 
-    let fields_count= fields.len();
+    // Flattened fields share this struct's object, so the names of their fields are only known at runtime
+    let known_field_names= field_input_names(fields);
+    let known_fields= if flattened_field_types(fields).is_empty() {
+        quote! {
+            let known_fields: &[&str]= &[#(#known_field_names),*];
+        }
+    } else {
+        quote! {
+            let mut known_fields= Vec::new();
+            Self::field_names(&mut known_fields);
 
-    let fields_validity_check= fields.iter().map(
-        |field| -> proc_macro2::TokenStream {
-            let field_ident= &field.ident;
-            quote! {
-                if !fields_map.contains_key(stringify!(#field_ident)) {
-                    let field_path= format!("{}.{}", context.get_path(), stringify!(#field_ident));
-                    println!("Deserialize object '{}' is missing field '{:?}'", stringify!(#item_ident), stringify!(#field_ident));
-                    println!("Field path '{}'", field_path);
-                    return Err(SchemaError::MissingField);
-                }
+            // Two of the structs sharing the object have a field with the same name
+            let duplicate= (1..known_fields.len()).find(|&index| known_fields[..index].contains(&known_fields[index]));
+            if let Some(index)= duplicate {
+                return Err(SchemaError::new(SchemaErrorKind::DuplicateField)
+                    .with_path(context.get_path())
+                    .with_type_name(stringify!(#item_ident))
+                    .with_expected("unique field names across flattened fields")
+                    .with_found(format!("more than one field named '{}'", known_fields[index]))
+                    .with_span(schema_value.span));
             }
         }
-    );
+    };
+
+    let handle_unknown_field= generate_unknown_field_handler(item_ident, &item_attributes.unknown_fields);
+    let post_load= crate::generate_post_load(item_ident, item_attributes);
+    let version_check= crate::generate_version_check(item_attributes);
 
     let fields_deserialize= fields.iter().map(
         |field| -> proc_macro2::TokenStream {
             let field_ident= &field.ident;
-            let field_type= &field.ty;
-            quote! {
-                // Deserialize the field given the schema value
-                #field_ident: {
-                    context.path.push(format!(".{}", stringify!(#field_ident).to_string()));
-                    let value= <#field_type>::deserialize(&fields_map[stringify!(#field_ident)], context)?;
-                    context.path.pop();
-                    value
+            let field_attributes= attributes::parse_attributes(&field.attrs);
+            if field_attributes.skip {
+                // Not in the definition file. The struct's default already holds its skip value, if it has one.
+                return quote! {
+                    #field_ident: schema_default.#field_ident
+                };
+            }
+
+            if field_attributes.flatten {
+                if field_attributes.with.is_some() || field_attributes.rename.is_some() || !field_attributes.aliases.is_empty() {
+                    panic!("Schema attribute flatten can't be combined with with, rename or alias");
                 }
+
+                let field_type= &field.ty;
+                return quote! {
+                    // Read from this struct's object, so there's no path to push
+                    #field_ident: {
+                        let offset_index= context.offset_index;
+                        match context.deserialize_flattened::<#field_type>(schema_value) {
+                            Ok(value) => value,
+                            Err(error) => context.recover::<#field_type>(error, schema_value, offset_index),
+                        }
+                    }
+                };
+            }
+
+            // Optional fields take their value from the struct's schema_default(), so that any
+            // #[schema_default(...)] markup is respected.
+            let optional= item_attributes.default || field_attributes.default;
+            let field_deserialize= generate_field_deserialize(item_ident, field, quote! { schema_default.#field_ident }, optional);
+            quote! {
+                #field_ident: #field_deserialize
             }
         });
 
+    // Skipped fields, missing optional fields and fields written as `default` are moved out of the struct's
+    // schema_default(), so it's only built once. Flattened fields have defaults of their own.
+    let schema_default= if fields.iter().any(|field| !attributes::parse_attributes(&field.attrs).flatten) {
+        quote! { let schema_default= Self::schema_default(); }
+    } else {
+        quote! {}
+    };
+
     quote! {
-        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<#item_ident> {
-            match schema_value {
-                SchemaValue::Object(fields_map) => {
-                    // Perform validity checks on the map
-                    #(#fields_validity_check)*
-
-                    if fields_map.len() != #fields_count {
-                        println!("Deserialize object {} contains extraneous unknown field(s).", stringify!(#item_ident));
-                        return Err(SchemaError::UnknownIdentifier);
+        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> {
+            #version_check
+
+            // A flattened struct shares its object with the enclosing struct, which checks for unknown fields
+            let flattened= std::mem::take(&mut context.flattened);
+
+            match &schema_value.kind {
+                SchemaValueKind::Object(fields_map) => {
+                    let error_count= context.errors.len();
+
+                    // Check for fields which aren't in this struct. Missing fields are reported as they are deserialized.
+                    if !flattened {
+                        #known_fields
+                        for field in fields_map.fields().filter(|field| !known_fields.contains(&field.name)) {
+                            #handle_unknown_field
+                        }
                     }
 
                     // Create the deserialized object with all of its deserialized fields
-                    Ok(#item_ident { #(#fields_deserialize),* })
+                    #schema_default
+                    let mut value= Self { #(#fields_deserialize),* };
+                    #post_load
+                    Ok(value)
                 },
                 _ => {
-                    println!("Deserialize hit a wrong value for field '{}'. Expected: Object({}), found: {:?}",
-                        context.get_path(),
-                        stringify!(#item_ident),
-                        schema_value);
-                    return Err(SchemaError::WrongSchemaValue);
+                    Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                        .with_type_name(stringify!(#item_ident))
+                        .with_expected("Object"))
                 }
             }
         }
     }
 }
+
+// Handles a `field` of `fields_map` which isn't one of the `known_fields`, as set by #[schema(unknown_fields = ...)]
+pub fn generate_unknown_field_handler(item_ident: &syn::Ident, unknown_fields: &attributes::UnknownFields)
+    -> proc_macro2::TokenStream {
+    // Names the unknown field, and lists the ones it could have been
+    let unknown_field_error= quote! {
+        {
+            context.path.push(format!(".{}", field.name));
+            let error= SchemaError::new(SchemaErrorKind::UnknownField)
+                .with_path(context.get_path())
+                .with_type_name(stringify!(#item_ident))
+                .with_expected(match known_fields.is_empty() {
+                    true => String::from("no fields"),
+                    false => format!("one of: {}", known_fields.join(", ")),
+                })
+                .with_found(format!("'{}'", field.name))
+                .with_span(field.name_span);
+            context.path.pop();
+            error
+        }
+    };
+
+    match unknown_fields {
+        attributes::UnknownFields::Deny => quote! { context.errors.push(#unknown_field_error); },
+        attributes::UnknownFields::Warn => quote! { context.warnings.push(#unknown_field_error); },
+        attributes::UnknownFields::Collect => quote! { context.collect_unknown_field(field); },
+    }
+}
+
+// Deserializes a named field of the object `schema_value`, whose fields are in `fields_map`. A field that is
+// missing or fails to deserialize is recorded in the context and replaced with its default value, so the
+// remaining fields are still checked. `default_value` is used for the `default` literal, and for a missing
+// field if it's `optional`.
+pub fn generate_field_deserialize(
+    item_ident: &syn::Ident,
+    field: &syn::Field,
+    default_value: proc_macro2::TokenStream,
+    optional: bool,
+) -> proc_macro2::TokenStream {
+    let field_ident= &field.ident;
+    let field_attributes= attributes::parse_attributes(&field.attrs);
+    let field_name= crate::schema_name(field_ident.as_ref().unwrap(), &field_attributes);
+    let field_lookup= generate_field_lookup(field);
+    let field_codec= field_codec(field);
+    let validation= generate_validation(item_ident, &field_attributes);
+
+    // A field written under more than one of its names, e.g. under its new name and an alias
+    let duplicate_check= if !field_attributes.aliases.is_empty() {
+        let input_names= crate::schema_input_names(field_ident.as_ref().unwrap(), &field_attributes);
+        quote! {
+            let input_names= [#(#input_names),*];
+            let mut written_fields= fields_map.fields().filter(|field| input_names.contains(&field.name));
+            if let (Some(first), Some(second))= (written_fields.next(), written_fields.next()) {
+                let error= SchemaError::new(SchemaErrorKind::DuplicateField)
+                    .with_path(context.get_path())
+                    .with_type_name(stringify!(#item_ident))
+                    .with_expected(format!("one of: {}", input_names.join(", ")))
+                    .with_found(format!("'{}' and '{}'", first.name, second.name))
+                    .with_span(second.name_span)
+                    .with_related_span(first.name_span);
+                context.errors.push(error);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let missing_field= if optional {
+        quote! {
+            #default_value
+        }
+    } else {
+        quote! {
+            let error= SchemaError::new(SchemaErrorKind::MissingField)
+                .with_path(context.get_path())
+                .with_type_name(stringify!(#item_ident))
+                .with_span(schema_value.span);
+            context.errors.push(error);
+            #field_codec::schema_default()
+        }
+    };
+
+    quote! {
+        // Deserialize the field given the schema value
+        {
+            context.path.push(format!(".{}", #field_name));
+            #duplicate_check
+            let value= match #field_lookup {
+                // The `default` literal, e.g. `speed: default`
+                Some(SchemaValue { kind: SchemaValueKind::Default, .. }) => #default_value,
+                Some(field_value) => {
+                    let offset_index= context.offset_index;
+                    match #field_codec::deserialize(field_value, context) {
+                        Ok(value) => {
+                            #validation
+                            value
+                        },
+                        Err(error) => context.recover_with(error, field_value, offset_index,
+                            #field_codec::build_layout, #field_codec::schema_default),
+                    }
+                },
+                None => {
+                    #missing_field
+                }
+            };
+            context.path.pop();
+            value
+        }
+    }
+}
+
+pub fn derive_field_names_fn(fields: &StructFields) -> proc_macro2::TokenStream {
+    let field_names= field_input_names(fields);
+    let flattened_field_types= flattened_field_types(fields);
+
+    quote! {
+        fn field_names(names: &mut Vec<&'static str>) {
+            names.extend_from_slice(&[#(#field_names),*]);
+            #(<#flattened_field_types>::field_names(names);)*
+        }
+    }
+}
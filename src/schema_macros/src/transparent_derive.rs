@@ -0,0 +1,88 @@
+extern crate proc_macro2;
+
+use quote::quote;
+
+use crate::attributes;
+use crate::struct_derive::{StructFields, field_codec, field_member, generate_validation};
+
+// #[schema(transparent)] structs, e.g. newtypes such as Meters(f32), are written exactly like their only field.
+// Field markup such as validation and `with` codecs still applies to that field. Other fields must be
+// skipped, e.g. a PhantomData.
+fn only_field(fields: &StructFields) -> (syn::Member, &syn::Field) {
+    let mut schema_fields= fields.iter().enumerate()
+        .filter(|(_, field)| !attributes::parse_attributes(&field.attrs).skip);
+    let (index, field)= match (schema_fields.next(), schema_fields.next()) {
+        (Some(only_field), None) => only_field,
+        _ => panic!("Schema attribute transparent requires a struct with exactly one field that isn't skipped"),
+    };
+
+    if attributes::parse_attributes(&field.attrs).flatten {
+        panic!("Schema attribute flatten can't be used on the field of a transparent struct");
+    }
+    (field_member(index, field), field)
+}
+
+pub fn derive_serialize_fn(fields: &StructFields) -> proc_macro2::TokenStream {
+    let (field_member, field)= only_field(fields);
+    let field_codec= field_codec(field);
+
+    quote! {
+        fn serialize(&self, context: &mut SerializeContext) {
+            #field_codec::serialize(&self.#field_member, context);
+        }
+    }
+}
+
+pub fn derive_build_layout_fn(fields: &StructFields) -> proc_macro2::TokenStream {
+    let (_, field)= only_field(fields);
+    let field_codec= field_codec(field);
+
+    quote! {
+        fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
+            -> Result<alloc::Layout, alloc::LayoutError> {
+            #field_codec::build_layout(schema_value, layout, offsets)
+        }
+    }
+}
+
+pub fn derive_deserialize_fn(
+    item_ident: &syn::Ident,
+    fields: &StructFields,
+    item_attributes: &attributes::SchemaAttributes,
+) -> proc_macro2::TokenStream {
+    let (field_member, field)= only_field(fields);
+    let field_codec= field_codec(field);
+    let validation= generate_validation(item_ident, &attributes::parse_attributes(&field.attrs));
+    let post_load= crate::generate_post_load(item_ident, item_attributes);
+    let version_check= crate::generate_version_check(item_attributes);
+
+    // As with any other struct, skipped fields are moved out of the struct's default, which holds their skip value
+    let skipped_fields: Vec<_>= fields.iter().enumerate()
+        .filter(|(_, field)| attributes::parse_attributes(&field.attrs).skip)
+        .map(|(index, field)| {
+            let skipped_member= crate::struct_derive::field_member(index, field);
+            quote! { #skipped_member: schema_default.#skipped_member }
+        })
+        .collect();
+    let schema_default= if skipped_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! { let schema_default= Self::schema_default(); }
+    };
+
+    quote! {
+        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> {
+            #version_check
+            let error_count= context.errors.len();
+
+            let field_value= schema_value;
+            let value= #field_codec::deserialize(field_value, context)?;
+            #validation
+
+            #schema_default
+            let mut value= Self { #field_member: value, #(#skipped_fields),* };
+            #post_load
+            Ok(value)
+        }
+    }
+}
@@ -0,0 +1,169 @@
+extern crate proc_macro2;
+
+use quote::quote;
+
+use crate::attributes;
+use crate::struct_derive::{StructFields, field_codec, field_member, generate_validation};
+
+// Tuple structs are written as an array of their fields, e.g. `position: [1.0, 2.0]` for Position(f32, f32).
+// Their fields are only ever known by their index, so markup that depends on a field name isn't supported.
+fn check_field_attributes(fields: &StructFields) {
+    for field in fields {
+        let field_attributes= attributes::parse_attributes(&field.attrs);
+        if field_attributes.skip || field_attributes.flatten || field_attributes.default
+            || field_attributes.rename.is_some() || !field_attributes.aliases.is_empty() {
+            panic!("Schema attributes skip, flatten, default, rename and alias are only supported on named fields");
+        }
+    }
+}
+
+pub fn derive_serialize_fn(fields: &StructFields) -> proc_macro2::TokenStream {
+    if fields.is_empty() {
+        return quote! {
+            fn serialize(&self, context: &mut SerializeContext) {
+                context.print("[]");
+            }
+        };
+    }
+
+    let fields_serialize= fields.iter().enumerate().map(
+        |(index, field)| -> proc_macro2::TokenStream {
+            let field_member= field_member(index, field);
+            let field_codec= field_codec(field);
+
+            let newline= if index == fields.len()-1 {
+                quote! {}
+            } else {
+                quote! {
+                    context.print(",\n");
+                    context.print_tabs();
+                }
+            };
+
+            quote! {
+                context.path.push(format!("[{}]", #index));
+                #field_codec::serialize(&self.#field_member, context);
+                context.path.pop();
+                #newline
+            }
+        });
+
+    quote! {
+        fn serialize(&self, context: &mut SerializeContext) {
+            context.print("[");
+            context.tabs+= 1;
+            context.println();
+            context.print_tabs();
+
+            #(#fields_serialize)*
+
+            context.tabs-= 1;
+            context.println();
+            context.print_tabs();
+            context.print("]");
+        }
+    }
+}
+
+pub fn derive_build_layout_fn(fields: &StructFields) -> proc_macro2::TokenStream {
+    let field_count= fields.len();
+    let fields_build_layout= fields.iter().enumerate().map(
+        |(index, field)| -> proc_macro2::TokenStream {
+            let field_codec= field_codec(field);
+            quote! {
+                let layout= #field_codec::build_layout(&vector[#index], layout, offsets)?;
+            }
+        });
+
+    quote! {
+        fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
+            -> Result<alloc::Layout, alloc::LayoutError> {
+            match &schema_value.kind {
+                // A wrongly sized array isn't deserialized, so nothing is allocated for it
+                SchemaValueKind::Array(vector) if vector.len() == #field_count => {
+                    #(#fields_build_layout)*
+
+                    Ok(layout)
+                },
+                _ => {
+                    Ok(layout)
+                }
+            }
+        }
+    }
+}
+
+pub fn derive_deserialize_fn(
+    item_ident: &syn::Ident,
+    fields: &StructFields,
+    item_attributes: &attributes::SchemaAttributes,
+) -> proc_macro2::TokenStream {
+    check_field_attributes(fields);
+
+    let field_count= fields.len();
+    let post_load= crate::generate_post_load(item_ident, item_attributes);
+    let version_check= crate::generate_version_check(item_attributes);
+
+    // As with named fields, a field that fails to deserialize is recorded in the context and replaced
+    // with its default value, so the remaining fields are still checked.
+    let fields_deserialize= fields.iter().enumerate().map(
+        |(index, field)| -> proc_macro2::TokenStream {
+            let field_member= field_member(index, field);
+            let field_codec= field_codec(field);
+            let validation= generate_validation(item_ident, &attributes::parse_attributes(&field.attrs));
+
+            quote! {
+                #field_member: {
+                    let field_value= &vector[#index];
+                    context.path.push(format!("[{}]", #index));
+                    let value= match field_value.kind {
+                        // The `default` literal, e.g. `[1.0, default]`
+                        SchemaValueKind::Default => schema_default.#field_member,
+                        _ => {
+                            let offset_index= context.offset_index;
+                            match #field_codec::deserialize(field_value, context) {
+                                Ok(value) => {
+                                    #validation
+                                    value
+                                },
+                                Err(error) => context.recover_with(error, field_value, offset_index,
+                                    #field_codec::build_layout, #field_codec::schema_default),
+                            }
+                        },
+                    };
+                    context.path.pop();
+                    value
+                }
+            }
+        });
+
+    quote! {
+        fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<Self> {
+            #version_check
+            match &schema_value.kind {
+                SchemaValueKind::Array(vector) => {
+                    if vector.len() != #field_count {
+                        return Err(context.error(SchemaErrorKind::WrongSizedArray, schema_value)
+                            .with_type_name(stringify!(#item_ident))
+                            .with_expected(format!("array of {} element(s)", #field_count)));
+                    }
+
+                    let error_count= context.errors.len();
+
+                    // Fields written as `default` are moved out of it, so it's only built once
+                    let schema_default= Self::schema_default();
+
+                    // Tuple structs can also be initialized by index, e.g. Point { 0: x, 1: y }
+                    let mut value= Self { #(#fields_deserialize),* };
+                    #post_load
+                    Ok(value)
+                },
+                _ => {
+                    Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                        .with_type_name(stringify!(#item_ident))
+                        .with_expected("Array"))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::*;
+
+use std::collections;
+use std::fmt;
+use std::ops;
+use std::slice;
+
+// A field of a schematized object, e.g. `name: <value>`
+#[derive(Debug)]
+pub struct SchemaField<'a> {
+    pub name: &'a str,
+    pub name_span: Span, // Location of the field name in the definition file
+    pub value: SchemaValue<'a>,
+}
+
+// The fields of a schematized object, kept in the order they were written in the definition file.
+// Fields can also be looked up by name, which is how the derived deserialize reads them.
+#[derive(Default)]
+pub struct SchemaObject<'a> {
+    fields: Vec<SchemaField<'a>>,
+    indices: collections::HashMap<&'a str, usize>, // field name -> index into `fields`
+}
+
+impl<'a> SchemaObject<'a> {
+    pub fn new() -> SchemaObject<'a> {
+        SchemaObject::default()
+    }
+
+    // Appends a field to the end of the object. If the object already has a field with this name,
+    // its value is replaced in place and the previous value is returned.
+    pub fn insert(&mut self, name: &'a str, name_span: Span, value: SchemaValue<'a>) -> Option<SchemaValue<'a>> {
+        match self.indices.get(name) {
+            Some(index) => {
+                let field= &mut self.fields[*index];
+                field.name_span= name_span;
+                Some(std::mem::replace(&mut field.value, value))
+            },
+            None => {
+                self.indices.insert(name, self.fields.len());
+                self.fields.push(SchemaField { name, name_span, value });
+                None
+            }
+        }
+    }
+
+    // Removes a field, keeping the order of the remaining fields.
+    pub fn remove(&mut self, name: &str) -> Option<SchemaField<'a>> {
+        let index= self.indices.remove(name)?;
+        let field= self.fields.remove(index);
+        for following_index in self.indices.values_mut() {
+            if *following_index > index {
+                *following_index-= 1;
+            }
+        }
+        Some(field)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SchemaValue<'a>> {
+        self.get_field(name).map(|field| &field.value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut SchemaValue<'a>> {
+        let index= *self.indices.get(name)?;
+        Some(&mut self.fields[index].value)
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<&SchemaField<'a>> {
+        self.indices.get(name).map(|index| &self.fields[*index])
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.indices.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    // Iterates over the fields in the order they were written
+    pub fn fields(&self) -> slice::Iter<'_, SchemaField<'a>> {
+        self.fields.iter()
+    }
+
+    // Iterates over the (name, value) pairs in the order they were written
+    pub fn iter(&self) -> impl Iterator<Item= (&'a str, &SchemaValue<'a>)> {
+        self.fields.iter().map(|field| (field.name, &field.value))
+    }
+}
+
+impl<'a> ops::Index<&str> for SchemaObject<'a> {
+    type Output= SchemaValue<'a>;
+
+    fn index(&self, name: &str) -> &SchemaValue<'a> {
+        match self.get(name) {
+            Some(value) => value,
+            None => panic!("SchemaObject has no field named '{}'", name),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for SchemaObject<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -10,8 +10,8 @@ pub struct SchemaString {
     len: usize,
 }
 
-impl<'a> SchemaString {
-    fn as_str(&self) -> Option<&'a str> {
+impl SchemaString {
+    pub fn as_str(&self) -> Option<&str> {
         if self.block_ptr.is_null() {
             None
         } else {
@@ -22,9 +22,32 @@ impl<'a> SchemaString {
         }
     }
 
-    //fn len(&self) -> usize {
-    //    self.len
-    //}
+    // The length of the string in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+// Write a string literal, escaping any characters which would otherwise not parse back to the same string.
+pub fn serialize_str(str: &str, context: &mut SerializeContext) {
+    context.print("\"");
+    for ch in str.chars() {
+        match ch {
+            '"' => context.print("\\\""),
+            '\\' => context.print("\\\\"),
+            '\n' => context.print("\\n"),
+            '\r' => context.print("\\r"),
+            '\t' => context.print("\\t"),
+            '\0' => context.print("\\0"),
+            _ if ch.is_control() => context.print(&format!("\\u{{{:x}}}", ch as u32)),
+            _ => context.print(ch.encode_utf8(&mut [0; 4])),
+        }
+    }
+    context.print("\"");
 }
 
 impl Schematize for SchemaString {
@@ -36,23 +59,14 @@ impl Schematize for SchemaString {
     }
 
     fn serialize(&self, context: &mut SerializeContext) {
-        match self.as_str() {
-            Some(str) => {
-                context.print("\"");
-                context.print(str);
-                context.print("\"");
-            },
-            None => {
-                context.print("\"\"");
-            }
-        }
+        serialize_str(self.as_str().unwrap_or(""), context);
     }
 
     fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
         -> Result<alloc::Layout, alloc::LayoutError> {
-        match schema_value {
-            SchemaValue::String(schema_string) => {
-                if schema_string.len() > 0 {
+        match &schema_value.kind {
+            SchemaValueKind::String(schema_string) => {
+                if !schema_string.is_empty() {
                     let string_layout= alloc::Layout::for_value(schema_string.as_bytes());
                     let (new_layout, offset)= layout.extend(string_layout)?;
                     offsets.push(offset);
@@ -71,10 +85,10 @@ impl Schematize for SchemaString {
     }
 
     fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<SchemaString> {
-        match schema_value {
-            SchemaValue::String(schema_string) => {
+        match &schema_value.kind {
+            SchemaValueKind::String(schema_string) => {
                 let bytes= schema_string.as_bytes();
-                if bytes.len() > 0 {
+                if !bytes.is_empty() {
                     // Get the block pointer offset for this string
                     let offset_index= context.offset_index;
                     assert!(offset_index < context.offsets.len());
@@ -84,9 +98,9 @@ impl Schematize for SchemaString {
 
                     unsafe {
                         // Copy the string bytes over
-                        let ptr= context.block_ptr.add(byte_offset) as *mut u8;
-                        for index in 0..bytes.len() {
-                            *ptr.add(index)= bytes[index];
+                        let ptr= context.block_ptr.add(byte_offset);
+                        for (index, byte) in bytes.iter().enumerate() {
+                            *ptr.add(index)= *byte;
                         }
 
                         Ok(SchemaString {
@@ -100,10 +114,9 @@ impl Schematize for SchemaString {
                 }
             },
             _ => {
-                println!("Deserialize hit a wrong value for field '{}'. Expected: String, found: {:?}",
-                    context.get_path(),
-                    schema_value);
-                Err(SchemaError::WrongSchemaValue)
+                Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                    .with_type_name("SchemaString")
+                    .with_expected("String"))
             }
         }
     }
@@ -111,10 +124,7 @@ impl Schematize for SchemaString {
 
 impl fmt::Debug for SchemaString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.as_str() {
-            Some(str) => write!(f, "\"{}\"", str),
-            None => write!(f, "\"\"")
-        }
+        write!(f, "{:?}", self.as_str().unwrap_or(""))
     }
 }
 
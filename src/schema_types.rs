@@ -4,6 +4,8 @@
 
 use crate::*;
 
+use std::any;
+
 macro_rules! schematize_num {
     ($type: ty, $cast_type: ty, $default_value: expr, $($schema_value:tt)*) => {
         impl Schematize for $type {
@@ -14,21 +16,19 @@ macro_rules! schematize_num {
             }
 
             fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<$type> {
-                match schema_value {
+                match &schema_value.kind {
                     $($schema_value)*(num) => {
-                        println!("Deserialize {}", *num);
                         if *num < <$type>::MIN as $cast_type || *num > <$type>::MAX as $cast_type {
-                            println!("Deserialize {} hit a value that is out of bounds {:?}", stringify!($type), schema_value);
-                            return Err(SchemaError::NumberOutOfBounds);
+                            return Err(context.error(SchemaErrorKind::NumberOutOfBounds, schema_value)
+                                .with_type_name(stringify!($type))
+                                .with_expected(format!("a value between {} and {}", <$type>::MIN, <$type>::MAX)));
                         }
                         Ok(*num as $type)
                     }
                     _ => {
-                        println!("Deserialize hit a wrong value for field '{}'. Expected: {}, found: {:?}",
-                            context.get_path(),
-                            stringify!($($schema_value)*),
-                            schema_value);
-                        return Err(SchemaError::WrongSchemaValue);
+                        Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                            .with_type_name(stringify!($type))
+                            .with_expected("Integer"))
                     }
                 }
             }
@@ -36,9 +36,54 @@ macro_rules! schematize_num {
     }
 }
 
-schematize_num!(u8,  i64, 0, SchemaValue::Integer);
-schematize_num!(i32, i64, 0, SchemaValue::Integer);
-schematize_num!(f32, f64, 0.0, SchemaValue::Decimal);
+macro_rules! schematize_float {
+    ($type: ty) => {
+        impl Schematize for $type {
+            fn schema_default() -> $type { 0.0 }
+
+            fn serialize(&self, context: &mut SerializeContext) {
+                // Debug formatting always writes a decimal point or an exponent (e.g. 5.0, 1e30),
+                // so the value reads back as a Decimal rather than an Integer that could overflow i64.
+                if self.is_nan() {
+                    context.print("nan");
+                } else {
+                    context.print(&format!("{:?}", self));
+                }
+            }
+
+            fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<$type> {
+                let num= match &schema_value.kind {
+                    SchemaValueKind::Decimal(num) => *num,
+                    // Allow integer literals for decimal fields, e.g. `speed: 5`
+                    SchemaValueKind::Integer(num) => *num as f64,
+                    _ => {
+                        return Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                            .with_type_name(stringify!($type))
+                            .with_expected("Decimal"));
+                    }
+                };
+
+                // inf and nan are valid for every float type, only finite values can be out of bounds
+                if num.is_finite() && (num < <$type>::MIN as f64 || num > <$type>::MAX as f64) {
+                    return Err(context.error(SchemaErrorKind::NumberOutOfBounds, schema_value)
+                        .with_type_name(stringify!($type))
+                        .with_expected(format!("a value between {:e} and {:e}", <$type>::MIN, <$type>::MAX)));
+                }
+                Ok(num as $type)
+            }
+        }
+    }
+}
+
+schematize_num!(u8,  i64, 0, SchemaValueKind::Integer);
+schematize_num!(u16, i64, 0, SchemaValueKind::Integer);
+schematize_num!(u32, i64, 0, SchemaValueKind::Integer);
+schematize_num!(i8,  i64, 0, SchemaValueKind::Integer);
+schematize_num!(i16, i64, 0, SchemaValueKind::Integer);
+schematize_num!(i32, i64, 0, SchemaValueKind::Integer);
+schematize_num!(i64, i64, 0, SchemaValueKind::Integer);
+schematize_float!(f32);
+schematize_float!(f64);
 
 impl Schematize for bool {
     fn schema_default() -> bool { false }
@@ -48,13 +93,12 @@ impl Schematize for bool {
     }
 
     fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<bool> {
-        match schema_value {
-            SchemaValue::Bool(schema_bool) => Ok(*schema_bool),
+        match &schema_value.kind {
+            SchemaValueKind::Bool(schema_bool) => Ok(*schema_bool),
             _ => {
-                println!("Deserialize hit a wrong value for field '{}'. Expected: Bool, found: {:?}",
-                    context.get_path(),
-                    schema_value);
-                return Err(SchemaError::WrongSchemaValue);
+                Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                    .with_type_name("bool")
+                    .with_expected("Bool"))
             }
         }
     }
@@ -70,26 +114,30 @@ impl<T: Schematize + Copy, const N: usize> Schematize for [T; N] {
     }
 
     fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<[T; N]> {
-        match schema_value {
-            SchemaValue::Array(schema_vector) => {
+        match &schema_value.kind {
+            SchemaValueKind::Array(schema_vector) => {
                 if schema_vector.len() != N {
-                    println!("Deserialize hit a static array of the wrong size for field '{}'. \
-                        Expected: {}, found: {}", context.get_path(), N, schema_vector.len());
-                    return Err(SchemaError::WrongSizedArray);
+                    return Err(context.error(SchemaErrorKind::WrongSizedArray, schema_value)
+                        .with_type_name(any::type_name::<[T; N]>())
+                        .with_expected(format!("array of {} element(s)", N)));
                 }
 
                 let mut array: [T; N]= [T::schema_default(); N];
                 for (index, item) in schema_vector.iter().enumerate() {
-                    array[index]= T::deserialize(item, context)?;
+                    context.path.push(format!("[{}]", index));
+                    let offset_index= context.offset_index;
+                    array[index]= match deserialize_or_default(item, context) {
+                        Ok(element) => element,
+                        Err(error) => context.recover(error, item, offset_index),
+                    };
+                    context.path.pop();
                 }
                 Ok(array)
             },
             _ => {
-                println!("Deserialize hit a wrong value for field '{}'. Expected: {}, found: {:?}",
-                    context.get_path(),
-                    stringify!([T; N]),
-                    schema_value);
-                return Err(SchemaError::WrongSchemaValue);
+                Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                    .with_type_name(any::type_name::<[T; N]>())
+                    .with_expected("Array"))
             }
         }
     }
@@ -0,0 +1,101 @@
+use crate::*;
+
+use std::alloc;
+
+// A variant the enum doesn't know, kept by its #[schema(other)] variant so that it's written back out
+// unchanged, e.g. `#[schema(other)] Unknown(UnknownVariant)`. There's no type to deserialize its fields into,
+// so they are kept as definition file text.
+#[derive(Debug)]
+pub struct UnknownVariant {
+    name: SchemaString,
+    fields: SchemaString,   // empty for a variant without fields
+}
+
+impl UnknownVariant {
+    pub fn name(&self) -> &str {
+        self.name.as_str().unwrap_or("")
+    }
+
+    // The fields of the variant as they were written, e.g. `count: 3` for `Spawn { count: 3 }`
+    pub fn fields(&self) -> Option<&str> {
+        self.fields.as_str()
+    }
+}
+
+// The fields of a variant as definition file text, written without indentation
+fn fields_text(enum_field: &SchemaValue) -> String {
+    let mut context= SerializeContext::new();
+    enum_field.serialize_payload(&mut context);
+    context.string
+}
+
+// Both strings are kept in the block, as if they were written as string values
+fn string_value<'a>(string: &'a str, span: Span) -> SchemaValue<'a> {
+    SchemaValue {
+        kind: SchemaValueKind::String(string),
+        span,
+    }
+}
+
+impl Schematize for UnknownVariant {
+    fn schema_default() -> UnknownVariant {
+        UnknownVariant {
+            name: SchemaString::schema_default(),
+            fields: SchemaString::schema_default(),
+        }
+    }
+
+    fn serialize(&self, context: &mut SerializeContext) {
+        serialize_variant_name(self.name(), context);
+
+        if let Some(fields)= self.fields() {
+            // The fields were written without indentation
+            let mut indent= String::from("\n");
+            for _ in 0..context.tabs+1 {
+                indent.push_str("  ");
+            }
+
+            context.print(" {");
+            context.tabs+= 1;
+            context.println();
+            context.print_tabs();
+            context.print(&fields.replace('\n', &indent));
+            context.println();
+            context.tabs-= 1;
+            context.print_tabs();
+            context.print("}");
+        }
+    }
+
+    fn build_layout(schema_value: &SchemaValue, layout: alloc::Layout, offsets: &mut Vec<usize>)
+        -> Result<alloc::Layout, alloc::LayoutError> {
+        match &schema_value.kind {
+            SchemaValueKind::EnumVariant(name, enum_field) => {
+                let layout= SchemaString::build_layout(&string_value(name, schema_value.span), layout, offsets)?;
+                let fields= fields_text(enum_field);
+                SchemaString::build_layout(&string_value(&fields, enum_field.span), layout, offsets)
+            },
+            _ => {
+                // hit wrong schema value. no-op.
+                Ok(layout)
+            }
+        }
+    }
+
+    fn deserialize(schema_value: &SchemaValue, context: &mut DeserializeContext) -> SchemaResult<UnknownVariant> {
+        match &schema_value.kind {
+            SchemaValueKind::EnumVariant(name, enum_field) => {
+                let name= SchemaString::deserialize(&string_value(name, schema_value.span), context)?;
+                let fields= fields_text(enum_field);
+                let fields= SchemaString::deserialize(&string_value(&fields, enum_field.span), context)?;
+
+                Ok(UnknownVariant { name, fields })
+            },
+            _ => {
+                Err(context.error(SchemaErrorKind::WrongSchemaValue, schema_value)
+                    .with_type_name("UnknownVariant")
+                    .with_expected("EnumVariant"))
+            }
+        }
+    }
+}